@@ -0,0 +1,93 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use registry::configuration::parse_str;
+
+// `large_config` builds a configuration with `n` notification endpoints, health checkers and url
+// patterns, on top of a realistic base.
+fn large_config(n: usize) -> String {
+    let mut content = String::from(
+        "version: 0.1
+log:
+  level: info
+  fields:
+    service: registry
+storage:
+  s3:
+    region: us-east-1
+    bucket: registry
+  delete:
+    enabled: true
+auth:
+  htpasswd:
+    realm: basic-realm
+    path: /etc/registry/htpasswd
+http:
+  addr: \":5000\"
+  host: registry.example.com
+  headers:
+    X-Content-Type-Options: [nosniff]
+validation:
+  enabled: false
+  manifests:
+    urls:
+      allow:
+",
+    );
+    for i in 0..n {
+        content.push_str(&format!("        - ^https://cdn{}\\.example\\.com/\n", i));
+    }
+    content.push_str("      deny: []\nhealth:\n  tcp:\n");
+    for i in 0..n {
+        content.push_str(&format!(
+            "    - addr: backend-{}.internal:6379\n      interval: 10s\n      timeout: 3s\n",
+            i
+        ));
+    }
+    content.push_str("  file:\n");
+    for i in 0..n {
+        content.push_str(&format!(
+            "    - file: /var/run/registry/down-{}\n      interval: 10s\n",
+            i
+        ));
+    }
+    content.push_str("notifications:\n  endpoints:\n");
+    for i in 0..n {
+        content.push_str(&format!(
+            "    - name: endpoint-{}
+      url: https://hooks.example.com/{}
+      headers:
+        Authorization: [Bearer token-{}]
+      timeout: 1s
+      threshold: 5
+      backoff: 2s
+      ignoredmediatypes: []
+",
+            i, i, i
+        ));
+    }
+    content
+}
+
+fn parse_validate(c: &mut Criterion) {
+    let content = large_config(200);
+    c.bench_function("parse 200", |b| b.iter(|| parse_str(black_box(&content)).unwrap()));
+
+    let config = parse_str(&content).unwrap();
+    c.bench_function("validate 200", |b| {
+        b.iter(|| black_box(&config).validate().unwrap())
+    });
+    c.bench_function("warnings 200", |b| b.iter(|| black_box(&config).warnings()));
+    c.bench_function("parse and validate 200", |b| {
+        b.iter(|| parse_str(black_box(&content)).unwrap().validate().unwrap())
+    });
+
+    // Five times the entries should cost about five times as much: a check which compares every
+    // endpoint against every other shows up here as a twenty-five fold jump.
+    let content = large_config(1000);
+    c.bench_function("parse and validate 1000", |b| {
+        b.iter(|| parse_str(black_box(&content)).unwrap().validate().unwrap())
+    });
+}
+
+criterion_group!(benches, parse_validate);
+criterion_main!(benches);
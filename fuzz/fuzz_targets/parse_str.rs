@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use registry::configuration::parse_str;
+
+// Whatever the input, loading a configuration must return an error rather than panic, and so must
+// the checks run on a configuration which did load.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(content) = std::str::from_utf8(data) {
+        if let Ok(config) = parse_str(&content) {
+            let _ = config.warnings();
+            let _ = config.lint();
+            let _ = config.summary();
+            let _ = config.serialize_redacted();
+        }
+    }
+});
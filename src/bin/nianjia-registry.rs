@@ -1,10 +1,17 @@
 use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
+use semver::Version;
 
 use nianjia::core::shell::Shell;
 
-use registry::configuration::parse_file;
+use registry::configuration::{
+	parse_file, parse_str, parse_url, set_in_place, template, ConfigError, Configuration,
+	TEMPLATE_STORAGE,
+};
 
 fn main() {
 	let matches = App::new("nianjia-registry")
@@ -16,22 +23,336 @@ fn main() {
 				.short("c")
 				.long("config")
 				.value_name("FILE")
-				.help("Sets a custom config file")
-				.takes_value(true),
+				.help(
+					"Sets a custom config file, or the file, http or https url of one. Without \
+					 it, the config is read from NIANJIA_CONFIG, under NIANJIA_HOME if relative, \
+					 then from default.conf",
+				)
+				.takes_value(true)
+				.global(true),
+		)
+		.arg(
+			Arg::with_name("print")
+				.long("print")
+				.help("Prints the config, with secrets redacted, and exits"),
+		)
+		.arg(
+			Arg::with_name("minimal")
+				.long("minimal")
+				.requires("print")
+				.help("Leaves the settings which hold their default out of --print"),
+		)
+		.arg(
+			Arg::with_name("strict")
+				.long("strict")
+				.help("Fails validation on warnings and deprecated fields as well as errors"),
+		)
+		.arg(
+			Arg::with_name("set")
+				.long("set")
+				.value_name("KEY=VALUE")
+				.help(
+					"Sets KEY, a dotted path such as http.addr, to VALUE in the config file, \
+					 keeping its comments and layout, and exits",
+				)
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.conflicts_with("print"),
+		)
+		.subcommand(
+			SubCommand::with_name("check-connectivity").about(
+				"Validates the config, then dials redis, the notification endpoints and the \
+				 tcp health checks it references",
+			),
+		)
+		.subcommand(
+			SubCommand::with_name("lint").about(
+				"Reports settings in the config which go against best practice, such as \
+				 plaintext secrets",
+			),
+		)
+		.subcommand(
+			SubCommand::with_name("diff")
+				.about(
+					"Lists the settings which differ between two config files, with secrets \
+					 redacted, exiting with 5 if there are any",
+				)
+				.arg(Arg::with_name("old").value_name("OLD").required(true))
+				.arg(Arg::with_name("new").value_name("NEW").required(true)),
+		)
+		.subcommand(
+			SubCommand::with_name("gen-config")
+				.about("Writes a commented starting config")
+				.arg(
+					Arg::with_name("storage")
+						.long("storage")
+						.value_name("DRIVER")
+						.help("Sets the storage driver of the generated config")
+						.possible_values(TEMPLATE_STORAGE)
+						.default_value("filesystem"),
+				)
+				.arg(
+					Arg::with_name("output")
+						.short("o")
+						.long("output")
+						.value_name("FILE")
+						.help("Writes the config to FILE instead of stdout")
+						.takes_value(true),
+				),
 		)
 		.get_matches();
 
+	if let Some(matches) = matches.subcommand_matches("gen-config") {
+		gen_config(
+			matches.value_of("storage").unwrap_or("filesystem"),
+			matches.value_of("output"),
+		);
+		return;
+	}
+
+	if let Some(matches) = matches.subcommand_matches("diff") {
+		diff(
+			matches.value_of("old").unwrap_or_default(),
+			matches.value_of("new").unwrap_or_default(),
+		);
+		return;
+	}
+
 	println!("{:?}", env::var_os("NIANJIA_HOME"));
-	let config_file = matches.value_of("config").unwrap_or("default.conf");
-	match parse_file(config_file) {
-		Ok(cfg) => {
-			println!("{:?}", cfg);
+	// The config is the one given with -c, else the one NIANJIA_CONFIG names, else default.conf.
+	let config_file = match matches
+		.subcommand()
+		.1
+		.and_then(|matches| matches.value_of("config"))
+		.or_else(|| matches.value_of("config"))
+	{
+		Some(config_file) => config_file.to_string(),
+		None => env_config_file().unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string()),
+	};
+	let config_file = config_file.as_str();
+	if let Some(assignments) = matches.values_of("set") {
+		set_config(config_file, assignments.collect());
+		return;
+	}
+	// A config given as a url, such as one served by a config server, is fetched from it.
+	let parsed = if config_file.contains("://") {
+		parse_url(config_file)
+	} else {
+		parse_file(config_file)
+	};
+	let mut cfg = match parsed {
+		Ok(cfg) => cfg,
+		Err(e) => exit_with_config_error(ConfigError::from_load(e)),
+	};
+	// Lint the file as written, before the environment has a say.
+	if matches.subcommand_matches("lint").is_some() {
+		let findings = cfg.lint();
+		for finding in &findings {
+			println!("{}", finding);
+		}
+		if findings.is_empty() {
+			println!("no findings");
+		}
+		return;
+	}
+	match cfg.apply_env_overrides() {
+		Ok(overrides) => {
+			for o in overrides {
+				eprintln!("{}", o);
+			}
+		}
+		Err(e) => {
+			let mut shell = Shell::new();
+			nianjia::exit_with_error(e.into(), &mut shell)
+		}
+	}
+
+	for deprecation in cfg.deprecations() {
+		eprintln!("warning: {}", deprecation);
+	}
+	if let (Ok(required), Ok(running)) = (
+		cfg.requires_min_version(),
+		Version::parse(env!("CARGO_PKG_VERSION")),
+	) {
+		if required > running {
+			eprintln!(
+				"warning: the config uses settings introduced in {}, this registry is {} and \
+				 ignores them",
+				required, running
+			);
+		}
+	}
+
+	if matches.subcommand_matches("check-connectivity").is_some() {
+		check_connectivity(&cfg);
+		return;
+	}
+	if matches.is_present("print") {
+		let content = if matches.is_present("minimal") {
+			cfg.to_minimal_yaml_redacted()
+		} else {
+			cfg.effective().serialize_redacted()
+		};
+		match content {
+			Ok(content) => print!("{}", content),
+			Err(e) => {
+				let mut shell = Shell::new();
+				nianjia::exit_with_error(e.into(), &mut shell)
+			}
+		}
+		return;
+	}
+	if let Err(e) = cfg.validate() {
+		exit_with_config_error(ConfigError::Validation(e))
+	}
+	if matches.is_present("strict") {
+		if let Err(e) = cfg.warnings_as_errors() {
+			exit_with_config_error(ConfigError::Validation(e))
+		}
+	}
+	println!("{}", cfg);
+}
+
+// The exit codes of the registry, by what went wrong:
+//
+// - 0: success.
+// - 1: the config isn't valid yaml, or doesn't describe a configuration.
+// - 2: the config parses, but doesn't validate.
+// - 3: the config file, or a file it includes, can't be read.
+// - 4: check-connectivity couldn't reach a target.
+// - 5: diff found differences.
+//
+// Other failures exit as `nianjia::exit_with_error` does.
+const EXIT_PARSE: i32 = 1;
+const EXIT_VALIDATION: i32 = 2;
+const EXIT_IO: i32 = 3;
+const EXIT_UNREACHABLE: i32 = 4;
+const EXIT_DIFFERENT: i32 = 5;
+
+// The config file read when neither -c nor NIANJIA_CONFIG name one.
+const DEFAULT_CONFIG_FILE: &str = "default.conf";
+
+// `env_config_file` returns the config file named by NIANJIA_CONFIG, if set. A relative path is
+// resolved under NIANJIA_HOME, when that is set too, as containers set both rather than a working
+// directory.
+fn env_config_file() -> Option<String> {
+	let config_file = env::var("NIANJIA_CONFIG").ok().filter(|file| !file.is_empty())?;
+	match env::var_os("NIANJIA_HOME") {
+		Some(home) if !config_file.contains("://") && Path::new(&config_file).is_relative() => {
+			Some(Path::new(&home).join(&config_file).display().to_string())
+		}
+		_ => Some(config_file),
+	}
+}
+
+// `exit_with_config_error` reports `e` and exits with the code of its category.
+fn exit_with_config_error(e: ConfigError) -> ! {
+	eprintln!("error: {}", e);
+	process::exit(match e {
+		ConfigError::Parse { .. } => EXIT_PARSE,
+		ConfigError::Validation(_) => EXIT_VALIDATION,
+		ConfigError::Io { .. } => EXIT_IO,
+	})
+}
+
+// `check_connectivity` reports, per target, whether a connection could be opened within the
+// configured timeout. It exits with `EXIT_UNREACHABLE` if any target is unreachable.
+fn check_connectivity(cfg: &Configuration) {
+	let mut shell = Shell::new();
+	if let Err(e) = cfg.validate() {
+		exit_with_config_error(ConfigError::Validation(e))
+	}
+	let targets = match cfg.connectivity_targets() {
+		Ok(targets) => targets,
+		Err(e) => nianjia::exit_with_error(e.into(), &mut shell),
+	};
+	let mut failed = false;
+	for target in targets {
+		match target.dial() {
+			Ok(()) => println!("OK   {} ({})", target.key, target.addr),
+			Err(e) => {
+				failed = true;
+				println!("FAIL {} ({}): {}", target.key, target.addr, e);
+			}
 		}
+	}
+	if failed {
+		process::exit(EXIT_UNREACHABLE);
+	}
+}
+
+// `set_config` applies the `KEY=VALUE` assignments to the config file `file` in place, only
+// rewriting the lines they change, and checks that the result still parses before writing it.
+fn set_config(file: &str, assignments: Vec<&str>) {
+	let mut shell = Shell::new();
+	if file.contains("://") {
+		let e = failure::format_err!("--set edits a config file, not the url {}", file);
+		nianjia::exit_with_error(e.into(), &mut shell)
+	}
+	let mut content = match fs::read_to_string(file) {
+		Ok(content) => content,
+		Err(e) => exit_with_config_error(ConfigError::Io {
+			message: format!("failed to read {}: {}", file, e),
+			kind: e.kind(),
+		}),
+	};
+	for assignment in assignments {
+		let edited = match assignment.find('=') {
+			Some(eq) => set_in_place(&content, &assignment[..eq], &assignment[eq + 1..]),
+			None => Err(failure::format_err!(
+				"--set {} isn't of the form KEY=VALUE",
+				assignment
+			)),
+		};
+		content = match edited {
+			Ok(content) => content,
+			Err(e) => nianjia::exit_with_error(e.into(), &mut shell),
+		};
+	}
+	if let Err(e) = parse_str(&content) {
+		exit_with_config_error(ConfigError::from_load(e))
+	}
+	if let Err(e) = fs::write(file, content) {
+		nianjia::exit_with_error(failure::Error::from(e).into(), &mut shell)
+	}
+}
+
+// `diff` prints the settings which differ between the config files `old` and `new`, one per
+// line, and exits with `EXIT_DIFFERENT` if there are any.
+fn diff(old: &str, new: &str) {
+	let parse = |file: &str| match parse_file(file) {
+		Ok(cfg) => cfg,
+		Err(e) => exit_with_config_error(ConfigError::from_load(e)),
+	};
+	let changes = match parse(old).diff(&parse(new)) {
+		Ok(changes) => changes,
 		Err(e) => {
-			println!("{:?}", e);
 			let mut shell = Shell::new();
 			nianjia::exit_with_error(e.into(), &mut shell)
 		}
 	};
+	for change in &changes {
+		println!("{}", change);
+	}
+	if !changes.is_empty() {
+		process::exit(EXIT_DIFFERENT);
+	}
 }
 
+// `gen_config` writes the template config for `storage` to `output`, or to stdout.
+fn gen_config(storage: &str, output: Option<&str>) {
+	let mut shell = Shell::new();
+	let content = match template(storage) {
+		Ok(content) => content,
+		Err(e) => nianjia::exit_with_error(e.into(), &mut shell),
+	};
+	match output {
+		Some(path) => {
+			if let Err(e) = fs::write(path, content) {
+				nianjia::exit_with_error(failure::Error::from(e).into(), &mut shell)
+			}
+		}
+		None => print!("{}", content),
+	}
+}
@@ -0,0 +1,54 @@
+use failure::{bail, format_err};
+use rusoto_core::Region;
+use rusoto_secretsmanager::{GetSecretValueRequest, SecretsManager, SecretsManagerClient};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::references::{select_key, SecretProvider, SecretReference};
+
+// AwsSecretsManagerSecrets resolves `awssm://id#key` from the AWS Secrets Manager secret `id`, an
+// arn or a name, such as
+// `awssm://arn:aws:secretsmanager:us-east-1:123456789012:secret:registry#redis_password`. With a
+// `#key`, the secret holds json, as the console stores key/value secrets.
+pub struct AwsSecretsManagerSecrets {
+    client: SecretsManagerClient,
+}
+
+impl AwsSecretsManagerSecrets {
+    // `new` returns a provider for `region`, with the credentials of the environment, the
+    // profile or the instance role, as the aws cli finds them.
+    pub fn new(region: Region) -> AwsSecretsManagerSecrets {
+        AwsSecretsManagerSecrets {
+            client: SecretsManagerClient::new(region),
+        }
+    }
+}
+
+impl Default for AwsSecretsManagerSecrets {
+    // `default` reads the region from `AWS_DEFAULT_REGION` or `AWS_REGION`.
+    fn default() -> AwsSecretsManagerSecrets {
+        AwsSecretsManagerSecrets::new(Region::default())
+    }
+}
+
+impl SecretProvider for AwsSecretsManagerSecrets {
+    fn scheme(&self) -> &str {
+        "awssm"
+    }
+
+    fn resolve(&self, reference: &SecretReference) -> NianjiaResult<String> {
+        let request = GetSecretValueRequest {
+            secret_id: reference.path.clone(),
+            ..Default::default()
+        };
+        let response = self
+            .client
+            .get_secret_value(request)
+            .sync()
+            .map_err(|e| format_err!("failed to read secret {}: {}", reference.path, e))?;
+        match response.secret_string {
+            Some(secret) => select_key(&secret, reference),
+            None => bail!("secret {} holds binary data, not a string", reference.path),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration as StdDuration;
+
+use failure::format_err;
+use http::Uri;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::Configuration;
+
+// ConnectivityTarget is a network address the configuration expects the registry to reach.
+#[derive(Debug, PartialEq)]
+pub struct ConnectivityTarget {
+    // `key` is the yaml path of the setting the address came from.
+    pub key: String,
+    // `addr` is the `host:port` to dial.
+    pub addr: String,
+    // `timeout` bounds the connection attempt, if set.
+    pub timeout: Option<StdDuration>,
+}
+
+impl ConnectivityTarget {
+    // `dial` opens, and immediately drops, a tcp connection to the target.
+    pub fn dial(&self) -> NianjiaResult<()> {
+        let mut last_err = None;
+        for addr in self.addr.to_socket_addrs()? {
+            let result = match self.timeout {
+                Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+                None => TcpStream::connect(addr),
+            };
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        match last_err {
+            Some(e) => Err(e.into()),
+            None => Err(format_err!("{} did not resolve to any address", self.addr)),
+        }
+    }
+}
+
+impl Configuration {
+    // `connectivity_targets` lists the redis server, the enabled notification endpoints and the
+    // tcp health checks, each with its configured timeout.
+    pub fn connectivity_targets(&self) -> NianjiaResult<Vec<ConnectivityTarget>> {
+        let mut targets = Vec::new();
+        if let Some(redis) = self.redis.as_ref().filter(|redis| !redis.addr.is_empty()) {
+            targets.push(ConnectivityTarget {
+                key: "redis.addr".to_string(),
+                addr: redis.addr.clone(),
+                timeout: redis.dial_timeout(),
+            });
+        }
+        for (i, endpoint) in self.active_endpoints() {
+            let key = format!("notifications.endpoints[{}].url", i);
+            targets.push(ConnectivityTarget {
+                addr: url_addr(&key, &endpoint.url)?,
+                key,
+                timeout: endpoint.timeout.as_timeout(),
+            });
+        }
+        if let Some(health) = &self.health {
+            for (i, checker) in health.tcp_checkers.iter().enumerate() {
+                targets.push(ConnectivityTarget {
+                    key: format!("health.tcp[{}].addr", i),
+                    addr: checker.addr.clone(),
+                    timeout: checker.timeout.as_timeout(),
+                });
+            }
+        }
+        Ok(targets)
+    }
+}
+
+// `url_addr` derives the `host:port` to dial from an http(s) url.
+fn url_addr(key: &str, url: &str) -> NianjiaResult<String> {
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| format_err!("{} \"{}\" is not a valid url: {}", key, url, e))?;
+    let host = uri
+        .host()
+        .ok_or_else(|| format_err!("{} \"{}\" has no host", key, url))?;
+    let port = match uri.port_part() {
+        Some(port) => port.as_u16(),
+        None if uri.scheme_str() == Some("https") => 443,
+        None => 80,
+    };
+    Ok(format!("{}:{}", host, port))
+}
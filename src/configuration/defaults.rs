@@ -0,0 +1,60 @@
+use super::section::ConfigSection;
+use super::{Configuration, Duration};
+
+// The semantic defaults `apply_defaults_in_place` fills in for settings left empty or zero.
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+pub const DEFAULT_HTTP_NET: &str = "tcp";
+pub const DEFAULT_PROMETHEUS_PATH: &str = "/metrics";
+pub const DEFAULT_HEALTH_STATUS_CODE: i32 = 200;
+pub const DEFAULT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+pub const DEFAULT_STORAGE_DRIVER_THRESHOLD: u32 = 3;
+
+impl Configuration {
+    // `apply_defaults_in_place` writes out the defaults the registry assumes for settings which
+    // are left empty, or zero, so that they can be read and printed as they take effect:
+    //
+    // - `log.level` is `info`.
+    // - `http.net` is `tcp`.
+    // - `http.debug.prometheus.path` is `/metrics`.
+    // - `health.http[].statuscode` is 200.
+    // - the `interval` of the health checkers is 10s.
+    // - the storage driver checker, when enabled, runs every 10s with a `threshold` of 3.
+    //
+    // Applying the defaults again changes nothing.
+    pub fn apply_defaults_in_place(&mut self) {
+        for section in self.sections_mut() {
+            section.apply_defaults();
+        }
+    }
+
+    // `effective` returns a copy of the configuration with the defaults applied.
+    pub fn effective(&self) -> Configuration {
+        let mut config = self.clone();
+        config.apply_defaults_in_place();
+        config
+    }
+
+    // `semantically_eq` reports whether two configurations configure the registry alike, though
+    // they may be written differently: a setting left out equals the same setting written out at
+    // its default, and an optional section written out with nothing but defaults equals an
+    // absent one. Maps compare regardless of the order their keys are written in.
+    pub fn semantically_eq(&self, other: &Configuration) -> bool {
+        let (this, other) = (self.effective(), other.effective());
+        match (this.minimal_value(), other.minimal_value()) {
+            (Ok(this), Ok(other)) => this == other,
+            _ => this == other,
+        }
+    }
+}
+
+pub(super) fn fill(value: &mut String, default: &str) {
+    if value.trim().is_empty() {
+        *value = default.to_string();
+    }
+}
+
+pub(super) fn fill_interval(interval: &mut Duration) {
+    if interval.as_std() == std::time::Duration::from_secs(0) {
+        *interval = Duration(DEFAULT_CHECK_INTERVAL.into());
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use super::Configuration;
+
+// The deprecated fields, by dotted yaml path, with guidance on what to use instead.
+pub const DEPRECATED_FIELDS: &[(&str, &str)] = &[
+    ("loglevel", "use log.level instead"),
+    (
+        "validation.enabled",
+        "use validation.disabled instead, validation is on unless disabled",
+    ),
+    (
+        "compatibility.schema1",
+        "schema1 manifests are deprecated, push schema2 or OCI manifests instead",
+    ),
+    (
+        "compatibility.manifesturls",
+        "use validation.manifests.urls instead",
+    ),
+];
+
+// Deprecation reports a deprecated field which a configuration sets.
+#[derive(Debug, PartialEq)]
+pub struct Deprecation {
+    pub path: &'static str,
+    pub guidance: &'static str,
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is deprecated: {}", self.path, self.guidance)
+    }
+}
+
+impl Configuration {
+    // `deprecations` lists the deprecated fields the configuration sets.
+    pub fn deprecations(&self) -> Vec<Deprecation> {
+        DEPRECATED_FIELDS
+            .iter()
+            .filter(|(path, _)| self.uses_deprecated(path))
+            .map(|&(path, guidance)| Deprecation { path, guidance })
+            .collect()
+    }
+
+    fn uses_deprecated(&self, path: &str) -> bool {
+        match path {
+            "loglevel" => self.extra.contains_key("loglevel"),
+            "validation.enabled" => self.validation.enabled,
+            "compatibility.schema1" => {
+                let schema1 = &self.compatibility.schema1;
+                schema1.enabled || !schema1.trust_key.is_empty()
+            }
+            "compatibility.manifesturls" => !self.compatibility.manifest_urls.is_empty(),
+            _ => false,
+        }
+    }
+
+    // `normalize_legacy_fields` carries settings over from where older configurations put them
+    // to their canonical place. The rules of `compatibility.manifesturls` are added to those of
+    // `validation.manifests.urls`, skipping rules already there, so normalizing again changes
+    // nothing. The legacy settings are kept, to be reported by `deprecations`.
+    pub(super) fn normalize_legacy_fields(&mut self) {
+        let legacy = &self.compatibility.manifest_urls;
+        let urls = &mut self.validation.manifests.urls;
+        merge_rules(&legacy.allow, &mut urls.allow);
+        merge_rules(&legacy.deny, &mut urls.deny);
+    }
+}
+
+fn merge_rules(legacy: &[String], canonical: &mut Vec<String>) {
+    for rule in legacy {
+        if !canonical.contains(rule) {
+            canonical.push(rule.clone());
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::redact::{set_paths, REDACTED, SECRET_PATHS};
+use super::Configuration;
+
+// Change is a setting which differs between two configurations, by its dotted yaml path, such as
+// `http.addr` or `notifications.endpoints[0].url`. `old` and `new` are its values on either side,
+// None where the setting is missing, with the `SECRET_PATHS` redacted.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub path: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unset = "(unset)".to_string();
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            self.old.as_ref().unwrap_or(&unset),
+            self.new.as_ref().unwrap_or(&unset)
+        )
+    }
+}
+
+impl Configuration {
+    // `diff` lists the settings which differ between `self` and `other`, ordered by path. Each
+    // scalar is compared on its own, so a changed section is reported key by key, and a sequence
+    // element by element.
+    pub fn diff(&self, other: &Configuration) -> NianjiaResult<Vec<Change>> {
+        let old = serde_yaml::to_value(self)?;
+        let new = serde_yaml::to_value(other)?;
+        let secrets: BTreeSet<String> = set_paths(&old, SECRET_PATHS)
+            .into_iter()
+            .chain(set_paths(&new, SECRET_PATHS))
+            .map(|(path, _)| path)
+            .collect();
+        let (mut old_leaves, mut new_leaves) = (BTreeMap::new(), BTreeMap::new());
+        leaves(&old, String::new(), &mut old_leaves);
+        leaves(&new, String::new(), &mut new_leaves);
+
+        let paths: BTreeSet<&String> = old_leaves.keys().chain(new_leaves.keys()).collect();
+        let render = |path: &str, value: Option<&&Value>| {
+            value.map(|value| {
+                if secrets.contains(path) {
+                    REDACTED.to_string()
+                } else {
+                    scalar(value)
+                }
+            })
+        };
+        Ok(paths
+            .into_iter()
+            .filter(|path| old_leaves.get(*path) != new_leaves.get(*path))
+            .map(|path| Change {
+                path: path.clone(),
+                old: render(path, old_leaves.get(path)),
+                new: render(path, new_leaves.get(path)),
+            })
+            .collect())
+    }
+}
+
+// `leaves` collects the scalars, empty sections and empty sequences of `value` by their paths.
+fn leaves<'a>(value: &'a Value, path: String, found: &mut BTreeMap<String, &'a Value>) {
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let key = scalar(key);
+                let child_path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                leaves(child, child_path, found);
+            }
+        }
+        Value::Sequence(items) if !items.is_empty() => {
+            for (i, child) in items.iter().enumerate() {
+                leaves(child, format!("{}[{}]", path, i), found);
+            }
+        }
+        _ => {
+            found.insert(path, value);
+        }
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "~".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) if value.is_empty() => "\"\"".to_string(),
+        Value::String(value) => value.clone(),
+        Value::Sequence(_) => "[]".to_string(),
+        Value::Mapping(_) => "{}".to_string(),
+    }
+}
@@ -0,0 +1,180 @@
+use failure::{bail, format_err};
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+// `set_in_place` returns `content`, a configuration document, with the scalar at the dotted
+// `path`, such as `http.addr` or `storage.s3.region`, set to `value`, a yaml scalar. It edits the
+// text rather than the parsed configuration: only the line holding the key changes, and comments,
+// blank lines, key order and quoting are kept. Serializing a parsed `Configuration`, as `--print`
+// does, loses all of them.
+//
+// A key missing from its section is added at the end of it, along with any missing sections on
+// the way. Keys written in flow style, such as `log: {level: info}`, or inside sequences can't be
+// edited in place.
+pub fn set_in_place(content: &str, path: &str, value: &str) -> NianjiaResult<String> {
+    let parsed: Value = serde_yaml::from_str(value)
+        .map_err(|e| format_err!("can't set {}: {} isn't yaml: {}", path, value, e))?;
+    if let Value::Mapping(_) | Value::Sequence(_) = parsed {
+        bail!("can't set {}: {} isn't a yaml scalar", path, value);
+    }
+    let segments: Vec<&str> = path.split('.').collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        bail!("can't set {}: the path has an empty key", path);
+    }
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut start = 0;
+    let mut end = lines.len();
+    let mut parent_indent: Option<usize> = None;
+    for (i, segment) in segments.iter().enumerate() {
+        let indent = child_indent(&lines[start..end], parent_indent);
+        let found = (start..end)
+            .find(|&n| indentation(&lines[n]) == indent && key_of(&lines[n]) == Some(*segment));
+        let n = match found {
+            Some(n) => n,
+            None => {
+                let at = last_content_line(&lines[..end], start).map_or(start, |n| n + 1);
+                let added = nested_keys(&segments[i..], indent, value);
+                lines.splice(at..at, added);
+                break;
+            }
+        };
+        let block_end = block_end(&lines, n, indent);
+        let rest = value_of(&lines[n]);
+        let comment = comment_of(rest);
+        if i + 1 < segments.len() {
+            if !rest[..rest.len() - comment.len()].trim().is_empty() {
+                bail!(
+                    "can't set {}: {} isn't a block mapping",
+                    path,
+                    segments[..=i].join(".")
+                );
+            }
+            start = n + 1;
+            end = block_end;
+            parent_indent = Some(indent);
+            continue;
+        }
+        let nested = (n + 1..block_end).any(|m| is_content(&lines[m]));
+        if nested || rest.starts_with('|') || rest.starts_with('>') {
+            bail!("can't set {}: it holds more than a scalar", path);
+        }
+        let mut line = format!("{}{}: {}", " ".repeat(indent), segment, value);
+        if !comment.is_empty() {
+            line = format!("{} {}", line, comment);
+        }
+        lines[n] = line;
+        break;
+    }
+
+    let mut edited = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        edited.push('\n');
+    }
+    let document: Value = serde_yaml::from_str(&edited)
+        .map_err(|e| format_err!("can't set {} in place: {}", path, e))?;
+    let set = segments
+        .iter()
+        .try_fold(&document, |value, segment| value.get(*segment));
+    if set != Some(&parsed) {
+        bail!("can't set {} in place", path);
+    }
+    Ok(edited)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+// `is_content` tells lines holding yaml apart from blank lines and comments.
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+// `child_indent` returns the indentation of the keys in `block`, the lines of a mapping nested
+// under a key indented by `parent`. An empty block gets its keys two spaces in.
+fn child_indent(block: &[String], parent: Option<usize>) -> usize {
+    let fallback = parent.map_or(0, |indent| indent + 2);
+    block
+        .iter()
+        .find(|line| is_content(line) && !line.trim_start().starts_with("---"))
+        .map_or(fallback, |line| indentation(line))
+}
+
+// `block_end` returns the line after the value of the key on line `n`, indented by `indent`. A
+// sequence may be written at the indentation of its key.
+fn block_end(lines: &[String], n: usize, indent: usize) -> usize {
+    (n + 1..lines.len())
+        .find(|&m| {
+            let line = &lines[m];
+            is_content(line)
+                && (indentation(line) < indent
+                    || indentation(line) == indent && !line.trim_start().starts_with('-'))
+        })
+        .unwrap_or_else(|| lines.len())
+}
+
+fn last_content_line(lines: &[String], start: usize) -> Option<usize> {
+    (start..lines.len()).rev().find(|&n| is_content(&lines[n]))
+}
+
+// `key_of` returns the plain key a `key: value` line starts with.
+fn key_of(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let colon = trimmed.find(':')?;
+    let after = &trimmed[colon + 1..];
+    if !(after.is_empty() || after.starts_with(' ') || after.starts_with('\t')) {
+        return None;
+    }
+    let key = &trimmed[..colon];
+    if key.is_empty() || key.starts_with(|c| "-#\"'{[".contains(c)) {
+        return None;
+    }
+    Some(key)
+}
+
+// `value_of` returns what follows the key of a `key: value` line, comment included.
+fn value_of(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    trimmed
+        .find(':')
+        .map_or("", |colon| trimmed[colon + 1..].trim())
+}
+
+// `comment_of` returns the trailing comment of a value, which starts at a `#` after a space and
+// outside of quotes.
+fn comment_of(value: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in value.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return &value[i..],
+            None => {}
+        }
+        previous = c;
+    }
+    ""
+}
+
+// `nested_keys` returns the lines adding `segments`, each a section of the one before, indented
+// from `indent`, with the last one set to `value`.
+fn nested_keys(segments: &[&str], indent: usize, value: &str) -> Vec<String> {
+    let last = segments.len() - 1;
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let padding = " ".repeat(indent + 2 * i);
+            if i == last {
+                format!("{}{}: {}", padding, segment, value)
+            } else {
+                format!("{}{}:", padding, segment)
+            }
+        })
+        .collect()
+}
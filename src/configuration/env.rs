@@ -0,0 +1,196 @@
+use std::fmt;
+
+use failure::bail;
+use serde_yaml::{Mapping, Value};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::redact::{redact, SECRET_PATHS};
+use super::Configuration;
+
+// Environment variables starting with `ENV_PREFIX` override configuration fields. The rest of the
+// variable name is the yaml path of the field, upper cased and joined with `_`, e.g.
+// `REGISTRY_LOG_LEVEL` overrides `log.level`. Setting a field to the empty string, such as
+// `REGISTRY_REDIS_ADDR=`, turns off the subsystems keyed on it.
+pub const ENV_PREFIX: &str = "REGISTRY_";
+
+// `REGISTRY_<SECTION>_DISABLED=true` turns off a whole subsystem, whatever the file says:
+//
+// - `notifications` marks every endpoint disabled.
+// - `health` drops the section, and with it every health checker, including the storage driver
+//   checker.
+// - `proxy` drops the section, so the registry no longer acts as a pull through cache.
+// - `redis` drops the section, as if it were absent from the file.
+//
+// `false` leaves the section as configured.
+pub const DISABLEABLE_SECTIONS: &[&str] = &["notifications", "health", "proxy", "redis"];
+
+// EnvOverride records an environment variable which replaced a value set in the file. `old` and
+// `new` are rendered with the `SECRET_PATHS` redacted, since overrides are logged at startup.
+#[derive(Debug, PartialEq)]
+pub struct EnvOverride {
+    pub variable: String,
+    // `path` is the dotted yaml path of the overridden field.
+    pub path: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for EnvOverride {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} overrode {}: {} -> {}",
+            self.variable, self.path, self.old, self.new
+        )
+    }
+}
+
+impl Configuration {
+    // `merge_env_overlay` applies every `REGISTRY_*` variable in `vars` on top of the
+    // configuration. It returns the overrides which replaced a value from the file; variables
+    // filling in unset fields aren't reported.
+    pub fn merge_env_overlay<I>(&mut self, vars: I) -> NianjiaResult<Vec<EnvOverride>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        vars.sort();
+
+        let mut disabled = Vec::new();
+        vars.retain(|(variable, raw)| match disabled_section(variable) {
+            Some(section) => {
+                disabled.push((section, variable.clone(), raw.clone()));
+                false
+            }
+            None => true,
+        });
+
+        let mut root = serde_yaml::to_value(&*self)?;
+        let mut overrides = Vec::new();
+        for (variable, raw) in vars {
+            let segments: Vec<String> = variable[ENV_PREFIX.len()..]
+                .split('_')
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| segment.to_lowercase())
+                .collect();
+            if segments.is_empty() {
+                continue;
+            }
+            let mut node = &mut root;
+            for segment in &segments {
+                node = child(node, segment);
+            }
+            let old = node.clone();
+            *node = env_value(&old, &raw);
+            if is_set(&old) && old != *node {
+                overrides.push(EnvOverride {
+                    variable,
+                    path: segments.join("."),
+                    old: render_redacted(&segments, &old),
+                    new: render_redacted(&segments, node),
+                });
+            }
+        }
+        *self = serde_yaml::from_value(root)?;
+
+        for (section, variable, raw) in disabled {
+            match raw.trim() {
+                "true" | "1" => self.disable_section(section),
+                "false" | "0" | "" => {}
+                _ => bail!("{} must be true or false, got \"{}\"", variable, raw),
+            }
+        }
+        Ok(overrides)
+    }
+
+    fn disable_section(&mut self, section: &str) {
+        match section {
+            "notifications" => {
+                for endpoint in &mut self.notifications.endpoints {
+                    endpoint.disabled = true;
+                }
+            }
+            "health" => self.health = None,
+            "proxy" => self.proxy = None,
+            "redis" => self.redis = None,
+            _ => {}
+        }
+    }
+
+    // `apply_env_overrides` applies the `REGISTRY_*` variables of the process environment.
+    pub fn apply_env_overrides(&mut self) -> NianjiaResult<Vec<EnvOverride>> {
+        self.merge_env_overlay(std::env::vars())
+    }
+}
+
+// `disabled_section` returns the section a `REGISTRY_<SECTION>_DISABLED` variable turns off.
+fn disabled_section(variable: &str) -> Option<&'static str> {
+    let section = variable[ENV_PREFIX.len()..].strip_suffix("_DISABLED")?;
+    DISABLEABLE_SECTIONS
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(section))
+        .cloned()
+}
+
+// `child` returns the value under `key`, turning `node` into a mapping and inserting a null value
+// as needed.
+fn child<'a>(node: &'a mut Value, key: &str) -> &'a mut Value {
+    if node.as_mapping().is_none() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    let map = node.as_mapping_mut().expect("node was just made a mapping");
+    let key = Value::String(key.to_string());
+    if !map.contains_key(&key) {
+        map.insert(key.clone(), Value::Null);
+    }
+    map.get_mut(&key).expect("key was just inserted")
+}
+
+// `env_value` interprets `raw` as yaml, unless the value it replaces is a string, so that e.g. a
+// numeric password stays a string.
+fn env_value(old: &Value, raw: &str) -> Value {
+    if raw.is_empty() || old.is_string() {
+        return Value::String(raw.to_string());
+    }
+    serde_yaml::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+// `render_redacted` renders `value`, found at `segments`, with the `SECRET_PATHS` at or under it
+// redacted.
+fn render_redacted(segments: &[String], value: &Value) -> String {
+    let mut root = value.clone();
+    for segment in segments.iter().rev() {
+        let mut map = Mapping::new();
+        map.insert(Value::String(segment.clone()), root);
+        root = Value::Mapping(map);
+    }
+    redact(&mut root, SECRET_PATHS);
+    let redacted = segments
+        .iter()
+        .try_fold(&root, |node, segment| node.get(segment.as_str()));
+    render(redacted.unwrap_or(value))
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Null => "~".to_string(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_start_matches("---").trim().to_string())
+            .unwrap_or_default(),
+    }
+}
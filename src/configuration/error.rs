@@ -0,0 +1,84 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use super::{parse_file, Configuration, ValidationErrors};
+
+// ConfigError tells apart the ways loading a configuration fails, for callers which handle them
+// differently, such as the CLI choosing an exit code. It converts into `NianjiaError` like any
+// other error.
+#[derive(Debug)]
+pub enum ConfigError {
+    // `Io` is a config file, or a file it includes, which couldn't be read.
+    Io { message: String, kind: io::ErrorKind },
+    // `Parse` is a document which isn't yaml, or doesn't describe a configuration, along with
+    // where in the document the problem is, when that is known.
+    Parse {
+        message: String,
+        location: Option<Location>,
+    },
+    // `Validation` is a configuration which parses, but can't work.
+    Validation(ValidationErrors),
+}
+
+// Location is a position in a yaml document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ConfigError {
+    // `from_load` classifies an error returned by `parse_file` or `parse_str`, by the io or yaml
+    // error it was caused by. Anything else, such as an unsupported version, is a parse error.
+    pub fn from_load(e: failure::Error) -> ConfigError {
+        let message = e.to_string();
+        for cause in e.iter_chain() {
+            if let Some(io) = cause.downcast_ref::<io::Error>() {
+                return ConfigError::Io {
+                    message,
+                    kind: io.kind(),
+                };
+            }
+            if let Some(yaml) = cause.downcast_ref::<serde_yaml::Error>() {
+                let location = yaml.location().map(|location| Location {
+                    line: location.line(),
+                    column: location.column(),
+                });
+                return ConfigError::Parse { message, location };
+            }
+        }
+        ConfigError::Parse {
+            message,
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { message, .. } | ConfigError::Parse { message, .. } => {
+                f.write_str(message)
+            }
+            ConfigError::Validation(errors) => write!(f, "invalid configuration:\n{}", errors),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Validation(errors) => Some(errors),
+            _ => None,
+        }
+    }
+}
+
+// `load_file` parses the configuration file `file`, as `parse_file` does, and validates it,
+// reporting which of the steps failed.
+pub fn load_file(file: &str) -> Result<Configuration, ConfigError> {
+    let config = parse_file(file).map_err(ConfigError::from_load)?;
+    config.validate().map_err(ConfigError::Validation)?;
+    Ok(config)
+}
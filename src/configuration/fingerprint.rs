@@ -0,0 +1,46 @@
+use serde_yaml::{Mapping, Value};
+use sha2::{Digest, Sha256};
+
+use super::redact::{redact, SECRET_PATHS};
+use super::Configuration;
+
+impl Configuration {
+    // `fingerprint` returns the hex sha-256 of the effective configuration, so that a change can
+    // be detected by comparing two strings. It hashes the canonical yaml of the configuration:
+    // the defaults are applied and then left out, as `to_minimal_yaml` does, and every mapping is
+    // sorted by key. Configurations which are `semantically_eq` so share a fingerprint, however
+    // their keys are ordered.
+    //
+    // The `SECRET_PATHS` are masked: setting or clearing a secret changes the fingerprint, but
+    // rotating it doesn't, and the fingerprint can be logged without giving the secret away.
+    pub fn fingerprint(&self) -> String {
+        let config = self.effective();
+        let mut value = match config.minimal_value() {
+            Ok(value) => value,
+            Err(_) => serde_yaml::to_value(&config).expect("a configuration serializes to yaml"),
+        };
+        redact(&mut value, SECRET_PATHS);
+        sort_keys(&mut value);
+        let canonical = serde_yaml::to_string(&value).expect("a yaml value serializes");
+        format!("{:x}", Sha256::digest(canonical.as_bytes()))
+    }
+}
+
+// `sort_keys` orders the keys of every mapping in `value`, which otherwise keep the order they
+// were written or inserted in.
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            let mut entries: Vec<(Value, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by_key(|(key, _)| serde_yaml::to_string(key).unwrap_or_default());
+            let mut sorted = Mapping::new();
+            for (key, mut child) in entries {
+                sort_keys(&mut child);
+                sorted.insert(key, child);
+            }
+            *map = sorted;
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
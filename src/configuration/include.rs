@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{bail, ResultExt};
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::nesting::{check_nesting, MAX_NESTING_DEPTH};
+
+// A configuration file may be composed of others by listing them under the top-level `include`
+// key, as a single file name or a list of them, relative to the including file's directory:
+//
+//     include: [storage.yaml, notifications.yaml]
+//
+// The included files are merged in order, each on top of the previous one, and the including
+// file on top of them all. Mappings are merged key by key; anything else, sequences included, is
+// replaced. Included files may include others, but not one which is already being included.
+pub const INCLUDE_KEY: &str = "include";
+
+// `has_includes` reports whether `content` is a yaml mapping with an `include` key.
+pub(super) fn has_includes(content: &str) -> bool {
+    match serde_yaml::from_str::<Value>(content) {
+        Ok(Value::Mapping(map)) => map.contains_key(&Value::String(INCLUDE_KEY.to_string())),
+        _ => false,
+    }
+}
+
+// `load` reads `file` and returns its document merged on top of the documents it includes.
+pub(super) fn load(file: &Path) -> NianjiaResult<Value> {
+    let content = fs::read_to_string(file)
+        .with_context(|e| format!("failed to read config file \"{}\": {}", file.display(), e))?;
+    resolve_includes(file, &content)
+}
+
+// `resolve_includes` returns the document of `file`, holding `content`, merged on top of the
+// documents it includes.
+pub(super) fn resolve_includes(file: &Path, content: &str) -> NianjiaResult<Value> {
+    let mut stack = Vec::new();
+    resolve(file, content, &mut stack)
+}
+
+fn resolve(file: &Path, content: &str, stack: &mut Vec<PathBuf>) -> NianjiaResult<Value> {
+    let canonical = file
+        .canonicalize()
+        .with_context(|e| format!("failed to read config file \"{}\": {}", file.display(), e))?;
+    if let Some(i) = stack.iter().position(|path| *path == canonical) {
+        let cycle: Vec<String> = stack[i..]
+            .iter()
+            .chain(Some(&canonical))
+            .map(|path| format!("\"{}\"", path.display()))
+            .collect();
+        bail!("include cycle: {}", cycle.join(" -> "));
+    }
+    stack.push(canonical);
+
+    check_nesting(content, MAX_NESTING_DEPTH)?;
+    let mut document: Value = serde_yaml::from_str(content)
+        .with_context(|e| format!("failed to parse config file \"{}\": {}", file.display(), e))?;
+    let includes = match &mut document {
+        Value::Mapping(map) => map.remove(&Value::String(INCLUDE_KEY.to_string())),
+        Value::Null => None,
+        _ => bail!("config file \"{}\" is not a yaml mapping", file.display()),
+    };
+    let includes = match includes {
+        None | Some(Value::Null) => vec![],
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Sequence(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                Value::String(include) => Ok(include),
+                _ => bail!("include in \"{}\" must list file names", file.display()),
+            })
+            .collect::<NianjiaResult<_>>()?,
+        Some(_) => bail!("include in \"{}\" must list file names", file.display()),
+    };
+
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+    let mut merged = Value::Null;
+    for include in includes {
+        let path = dir.join(&include);
+        let content = fs::read_to_string(&path).with_context(|e| {
+            format!(
+                "failed to read config file \"{}\" included from \"{}\": {}",
+                path.display(),
+                file.display(),
+                e
+            )
+        })?;
+        merge(&mut merged, resolve(&path, &content, stack)?);
+    }
+    merge(&mut merged, document);
+    stack.pop();
+    Ok(merged)
+}
+
+// `merge` merges `overlay` on top of `base`.
+pub(super) fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
@@ -0,0 +1,109 @@
+use failure::bail;
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::nesting::{check_nesting, MAX_NESTING_DEPTH};
+use super::{is_empty_document, parse_v0_1, Configuration, Versioned};
+
+// The configuration version this registry understands.
+pub const SUPPORTED_VERSION: &str = "0.1";
+
+// VersionGap describes what `parse_lenient_versioned` left out of a document.
+#[derive(Debug, PartialEq)]
+pub struct VersionGap {
+    // `version` is the version the document was written for.
+    pub version: String,
+    // `unknown_fields` lists the dotted paths of the fields this version doesn't know, which
+    // take no effect.
+    pub unknown_fields: Vec<String>,
+}
+
+impl VersionGap {
+    // `is_empty` reports whether the document was understood in full.
+    pub fn is_empty(&self) -> bool {
+        self.version == SUPPORTED_VERSION && self.unknown_fields.is_empty()
+    }
+}
+
+// `parse_lenient_versioned` parses a document written for any version sharing the major version
+// of `SUPPORTED_VERSION`, such as one written for a newer minor version during a rolling
+// deployment. It loads the fields this version knows and reports the rest, rather than failing.
+pub fn parse_lenient_versioned<T: AsRef<str>>(
+    content: &T,
+) -> NianjiaResult<(Configuration, VersionGap)> {
+    let content = content.as_ref();
+    if is_empty_document(content) {
+        bail!("configuration is empty");
+    }
+    check_nesting(content, MAX_NESTING_DEPTH)?;
+    let versioned: Versioned = serde_yaml::from_str(content)?;
+    if major(&versioned.version) != major(SUPPORTED_VERSION) {
+        bail!(
+            "unsupported version \"{}\": this registry only understands configuration version {}",
+            versioned.version,
+            SUPPORTED_VERSION
+        );
+    }
+    let config = parse_v0_1(content)?;
+
+    let document: Value = serde_yaml::from_str(content)?;
+    let known = serde_yaml::to_value(&config)?;
+    let mut unknown_fields: Vec<String> = config.extra.keys().cloned().collect();
+    unknown(&document, &known, "", &mut unknown_fields);
+    unknown_fields.sort();
+    unknown_fields.dedup();
+    Ok((
+        config,
+        VersionGap {
+            version: versioned.version,
+            unknown_fields,
+        },
+    ))
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or("")
+}
+
+// `unknown` collects the keys of `document` which the parsed configuration, serialized as `known`,
+// lacks. Keys are compared ignoring case and `_`, so the snake_case and camelCase aliases match
+// their canonical keys.
+fn unknown(document: &Value, known: &Value, path: &str, fields: &mut Vec<String>) {
+    match (document, known) {
+        (Value::Mapping(document), Value::Mapping(known)) => {
+            for (key, value) in document {
+                let key = match key {
+                    Value::String(key) => key,
+                    _ => continue,
+                };
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let found = known.iter().find(|(known_key, _)| match known_key {
+                    Value::String(known_key) => normalize(known_key) == normalize(key),
+                    _ => false,
+                });
+                match found {
+                    Some((_, known)) => unknown(value, known, &child, fields),
+                    None => fields.push(child),
+                }
+            }
+        }
+        (Value::Sequence(document), Value::Sequence(known)) => {
+            for (i, (value, known)) in document.iter().zip(known).enumerate() {
+                unknown(value, known, &format!("{}[{}]", path, i), fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
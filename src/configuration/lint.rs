@@ -0,0 +1,108 @@
+use std::fmt;
+
+use super::redact::{set_paths, SECRET_PATHS};
+use super::references::is_reference;
+use super::secrets::SECRET_SCHEME;
+use super::{is_inline_pem, Configuration, StorageMedia};
+
+// A url no sensible allow list permits, used to tell whether the allow list permits anything.
+const UNLIKELY_URL: &str = "gopher://lint.invalid:1/unlikely";
+
+// Severity ranks a lint finding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => f.write_str("info"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+// Finding is a best-practice issue reported by `Configuration::lint`.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    fn new<T: Into<String>>(severity: Severity, message: T) -> Finding {
+        Finding {
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl Configuration {
+    // `lint` reports settings which work, but go against best practice. Unlike `validate` it
+    // never rejects a configuration; the `warnings` are part of its findings.
+    pub fn lint(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if let Ok(value) = serde_yaml::to_value(self) {
+            for (path, secret) in set_paths(&value, SECRET_PATHS) {
+                if let serde_yaml::Value::String(secret) = secret {
+                    // The path of a key file isn't a secret, the key written inline is.
+                    if is_reference(secret) || (is_key_path(&path) && !is_inline_pem(secret)) {
+                        continue;
+                    }
+                }
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    format!(
+                        "{} holds a plaintext secret, reference it as {}<name> or set it \
+                         through the environment",
+                        path, SECRET_SCHEME
+                    ),
+                ));
+            }
+        }
+        if let StorageMedia::InMemory = self.storage.media {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "storage.inmemory keeps everything in memory, it is lost on restart",
+            ));
+        }
+        if self.http.secret.is_empty() {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "http.secret is not set, a random one is generated at start, which breaks \
+                 uploads across restarts and between instances",
+            ));
+        }
+        let urls = &self.validation.manifests.urls;
+        if !urls.allow.is_empty() && self.validation.url_permitted(UNLIKELY_URL) {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "validation.manifests.urls.allow permits any url",
+            ));
+        }
+        if self.log.access_log.disabled {
+            findings.push(Finding::new(
+                Severity::Info,
+                "log.access_log.disabled is set, requests won't be logged",
+            ));
+        }
+        for warning in self.warnings() {
+            findings.push(Finding::new(Severity::Warning, warning));
+        }
+        findings
+    }
+}
+
+// `is_key_path` tells the fields holding a private key or the path of its file.
+fn is_key_path(path: &str) -> bool {
+    path == "http.tls.key" || path.ends_with(".clientkey")
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use failure::{bail, format_err};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::parameters::{ParameterError, ParametersExt};
+use super::validation::resolve;
+use super::{Configuration, Middleware, Parameter};
+
+// The options each known middleware requires, by middleware name. The options of a middleware
+// which isn't listed are passed through unchecked.
+pub const MIDDLEWARE_REQUIRED_OPTIONS: &[(&str, &[&str])] = &[
+    (
+        CLOUDFRONT_MIDDLEWARE,
+        &["baseurl", "privatekey", "keypairid"],
+    ),
+    ("redirect", &["baseurl"]),
+];
+
+impl Middleware {
+    // `option` returns the option `key`, or None when it is missing or a yaml null. The
+    // `ParametersExt` accessors read typed values from `options`.
+    pub fn option(&self, key: &str) -> Option<&Parameter> {
+        match self.options.parameters.get(key) {
+            None | Some(Parameter::Null) => None,
+            Some(value) => Some(value),
+        }
+    }
+
+    // `required_options` returns the options the middleware requires, none for a middleware
+    // which isn't known.
+    pub fn required_options(&self) -> &'static [&'static str] {
+        match MIDDLEWARE_REQUIRED_OPTIONS
+            .iter()
+            .find(|(name, _)| *name == self.name)
+        {
+            Some(&(_, options)) => options,
+            None => &[],
+        }
+    }
+
+    // `missing_options` lists the required options which are missing, null or empty.
+    pub(super) fn missing_options(&self) -> Vec<&'static str> {
+        self.required_options()
+            .iter()
+            .cloned()
+            .filter(|key| match self.option(key) {
+                None => true,
+                Some(Parameter::String(value)) => value.trim().is_empty(),
+                Some(_) => false,
+            })
+            .collect()
+    }
+}
+
+// The name of the storage middleware which serves blobs through signed CloudFront urls.
+pub const CLOUDFRONT_MIDDLEWARE: &str = "cloudfront";
+// How long signed CloudFront urls stay valid when `duration` is unset.
+pub const DEFAULT_CLOUDFRONT_DURATION: Duration = Duration::from_secs(20 * 60);
+
+// CloudFrontMiddleware is the typed form of the options of the `cloudfront` storage middleware.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudFrontMiddleware {
+    pub baseurl: String,
+    // `privatekey` is the path of the pem key the urls are signed with.
+    pub privatekey: String,
+    pub keypairid: String,
+    // `duration` is how long a signed url stays valid.
+    pub duration: Duration,
+}
+
+impl CloudFrontMiddleware {
+    // `from_options` reads the options of the middleware at `section`, such as
+    // `middleware.storage[0]`.
+    pub(super) fn from_options(
+        section: &str,
+        options: &BTreeMap<String, Parameter>,
+    ) -> NianjiaResult<CloudFrontMiddleware> {
+        let section = format!("{}.options", section);
+        let qualify = |e: ParameterError| e.in_section(&section);
+        let duration = match options.get("duration") {
+            None | Some(Parameter::Null) => DEFAULT_CLOUDFRONT_DURATION,
+            Some(Parameter::Integer(secs)) if *secs > 0 => Duration::from_secs(*secs as u64),
+            Some(Parameter::String(duration)) => {
+                match humantime::parse_duration(duration)
+                    .map_err(|e| format_err!("{}.duration \"{}\": {}", section, duration, e))?
+                {
+                    duration if duration > Duration::from_secs(0) => duration,
+                    _ => bail!("{}.duration must be a positive duration", section),
+                }
+            }
+            Some(_) => bail!("{}.duration must be a positive duration", section),
+        };
+        Ok(CloudFrontMiddleware {
+            baseurl: options.require_str("baseurl").map_err(qualify)?.to_string(),
+            privatekey: options
+                .require_str("privatekey")
+                .map_err(qualify)?
+                .to_string(),
+            keypairid: options
+                .require_str("keypairid")
+                .map_err(qualify)?
+                .to_string(),
+            duration,
+        })
+    }
+
+    // `validate` checks that the private key of the middleware at `section` exists, resolving
+    // its path under `root`, if any, as `validate_paths_relative_to` does.
+    pub fn validate(&self, section: &str, root: Option<&Path>) -> NianjiaResult<()> {
+        if !resolve(root, Path::new(&self.privatekey)).is_file() {
+            bail!(
+                "{}.options.privatekey \"{}\" does not exist",
+                section,
+                self.privatekey
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Configuration {
+    // `cloudfront_middleware` returns the validated options of the first enabled `cloudfront`
+    // storage middleware, if any.
+    pub fn cloudfront_middleware(&self) -> Option<NianjiaResult<CloudFrontMiddleware>> {
+        let (_, i, middleware) =
+            self.enabled_middlewares()
+                .find(|(subsystem, _, middleware)| {
+                    *subsystem == "storage" && middleware.name == CLOUDFRONT_MIDDLEWARE
+                })?;
+        let section = format!("middleware.storage[{}]", i);
+        Some(
+            CloudFrontMiddleware::from_options(&section, &middleware.options.parameters)
+                .and_then(|cloudfront| cloudfront.validate(&section, None).map(|()| cloudfront)),
+        )
+    }
+}
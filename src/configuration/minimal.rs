@@ -0,0 +1,130 @@
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::redact::{redact, SECRET_PATHS};
+use super::{
+    parse_v0_1, unset_interval, Configuration, FileChecker, Health, HttpChecker, TcpChecker,
+};
+
+// The dotted yaml paths of the keys without a serde default, which a configuration doesn't parse
+// without. `*` matches any key of a mapping or any element of a sequence.
+const REQUIRED_PATHS: &[&str] = &[
+    "version",
+    "log",
+    "reporting.bugsnag",
+    "reporting.bugsnag.apikey",
+    "reporting.newrelic.licensekey",
+    "http.headers",
+    "http.tls.clientcas",
+    "http.tls.letsencrypt.cachefile",
+    "http.tls.letsencrypt.email",
+    "notifications.endpoints",
+    "notifications.endpoints.*.name",
+    "notifications.endpoints.*.url",
+    "notifications.endpoints.*.headers",
+    "notifications.endpoints.*.ignoredmediatypes",
+    "notifications.endpoints.*.ignore.actions",
+    "notifications.events.include_references",
+    "validation.enabled",
+    "validation.manifests",
+    "validation.manifests.urls",
+    "policy.repository",
+    "policy.repository.classes",
+    "health.http.*.headers",
+];
+
+impl Configuration {
+    // `to_minimal_yaml` serializes only what deviates from the defaults: keys holding their
+    // default value are left out, as are sections left empty by that. Keys without a serde
+    // default, the `REQUIRED_PATHS`, are kept in every section which is, so that the output parses
+    // back to the same configuration.
+    pub fn to_minimal_yaml(&self) -> NianjiaResult<String> {
+        Ok(serde_yaml::to_string(&self.minimal_value()?)?)
+    }
+
+    // `to_minimal_yaml_redacted` is `to_minimal_yaml` with the `SECRET_PATHS` redacted.
+    pub fn to_minimal_yaml_redacted(&self) -> NianjiaResult<String> {
+        let mut value = self.minimal_value()?;
+        redact(&mut value, SECRET_PATHS);
+        Ok(serde_yaml::to_string(&value)?)
+    }
+
+    pub(super) fn minimal_value(&self) -> NianjiaResult<Value> {
+        let mut defaults = parse_v0_1(&format!("version: \"{}\"\nlog: {{}}\n", self.version))?;
+        // An optional section which is present is pruned against its defaults like any other.
+        defaults.redis = Some(Default::default());
+        defaults.proxy = Some(Default::default());
+        // The elements of a sequence are pruned against the one element of its defaults.
+        defaults.notifications.endpoints = vec![Default::default()];
+        defaults.health = Some(Health {
+            file_checkers: vec![FileChecker {
+                interval: unset_interval(),
+                ..Default::default()
+            }],
+            http_checkers: vec![HttpChecker {
+                interval: unset_interval(),
+                ..Default::default()
+            }],
+            tcp_checkers: vec![TcpChecker {
+                interval: unset_interval(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let defaults = serde_yaml::to_value(&defaults)?;
+        let mut value = serde_yaml::to_value(self)?;
+        prune(&mut value, &defaults, &[]);
+        Ok(value)
+    }
+}
+
+// `prune` removes the entries of `value`, found at `path`, which are equal to those of
+// `defaults`, and reports whether nothing but defaults is left of `value`. Entries `defaults`
+// lacks, such as the keys of free-form maps, are kept, and so are the `REQUIRED_PATHS`, pruned
+// themselves. The elements of a sequence whose defaults hold one element are pruned against it.
+fn prune(value: &mut Value, defaults: &Value, path: &[&str]) -> bool {
+    match (value, defaults) {
+        (Value::Mapping(map), Value::Mapping(defaults)) => {
+            let mut left = false;
+            let mut pruned = Vec::new();
+            for (key, child) in map.iter_mut() {
+                let key_path = match key {
+                    Value::String(key) => [path, &[key.as_str()]].concat(),
+                    _ => Vec::new(),
+                };
+                match defaults.get(key) {
+                    Some(default) if prune(child, default, &key_path) => {
+                        if !is_required(&key_path) {
+                            pruned.push(key.clone());
+                        }
+                    }
+                    _ => left = true,
+                }
+            }
+            for key in pruned {
+                map.remove(&key);
+            }
+            !left
+        }
+        (Value::Sequence(items), Value::Sequence(defaults)) if defaults.len() == 1 => {
+            let item_path = [path, &["*"]].concat();
+            for item in items.iter_mut() {
+                prune(item, &defaults[0], &item_path);
+            }
+            items.is_empty()
+        }
+        (value, defaults) => value == defaults,
+    }
+}
+
+fn is_required(path: &[&str]) -> bool {
+    REQUIRED_PATHS.iter().any(|required| {
+        let segments: Vec<&str> = required.split('.').collect();
+        segments.len() == path.len()
+            && segments
+                .iter()
+                .zip(path)
+                .all(|(segment, key)| *segment == "*" || segment == key)
+    })
+}
@@ -0,0 +1,108 @@
+use failure::bail;
+
+use nianjia::util::errors::NianjiaResult;
+
+// The deepest nesting of mappings and sequences a configuration may use, unless the caller sets
+// its own limit. Real configurations stay under ten levels; the limit is there to reject hostile
+// documents before the yaml parser recurses deep enough to overflow the stack.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+// `check_nesting` fails if `content` nests mappings and sequences deeper than `max_depth`.
+//
+// It runs over the text, before any yaml is parsed, so it can't be made to recurse itself. A level
+// is counted for each step of indentation, each `- ` sequence entry opened on a line and each open
+// `[` or `{` of a flow collection. The count is an estimate, but a document under the limit is
+// never rejected for being formatted compactly.
+pub(super) fn check_nesting(content: &str, max_depth: usize) -> NianjiaResult<()> {
+    let mut indents: Vec<usize> = Vec::new();
+    let mut flow = 0usize;
+    // The indentation of the line which opened a block scalar, whose content isn't yaml.
+    let mut block_scalar: Option<usize> = None;
+    for line in content.lines() {
+        let text = line.trim_start_matches(' ');
+        let indent = line.len() - text.len();
+        if let Some(parent) = block_scalar {
+            if text.trim().is_empty() || indent > parent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        let text = strip_comment(text).trim_end();
+        if text.is_empty() || text == "---" || text == "..." {
+            continue;
+        }
+        if flow == 0 {
+            while indents.last().map_or(false, |&last| last > indent) {
+                indents.pop();
+            }
+            if indents.last().map_or(true, |&last| last < indent) {
+                indents.push(indent);
+            }
+        }
+
+        let mut rest = text;
+        let mut entries = 0;
+        while rest == "-" || rest.starts_with("- ") {
+            entries += 1;
+            rest = rest[1..].trim_start();
+        }
+        let mut deepest = flow;
+        let mut quote = None;
+        let mut previous = ' ';
+        for c in rest.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"') | (None, '\'') if opens_quote(previous) => quote = Some(c),
+                (None, '[') | (None, '{') => {
+                    flow += 1;
+                    deepest = deepest.max(flow);
+                }
+                (None, ']') | (None, '}') => flow = flow.saturating_sub(1),
+                _ => {}
+            }
+            previous = c;
+        }
+        if indents.len() + entries + deepest > max_depth {
+            bail!("configuration nesting exceeds maximum depth of {}", max_depth);
+        }
+        if flow == 0 && is_block_header(rest) {
+            block_scalar = Some(indent);
+        }
+    }
+    Ok(())
+}
+
+// `strip_comment` drops a trailing `#` comment from `text`, leaving any `#` inside quotes.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') if opens_quote(previous) => quote = Some(c),
+            (None, '#') if previous == ' ' || previous == '\t' => return &text[..i],
+            _ => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+// `opens_quote` reports whether a quote following `previous` starts a quoted scalar, rather than
+// being part of a plain one such as `don't`.
+fn opens_quote(previous: char) -> bool {
+    " \t:,[{".contains(previous)
+}
+
+// `is_block_header` reports whether `text` ends with a block scalar header, such as `|`, `>` or
+// `|-`, after which the lines indented further are text rather than yaml.
+fn is_block_header(text: &str) -> bool {
+    let header = text.rsplit(' ').next().unwrap_or(text);
+    let mut chars = header.chars();
+    match chars.next() {
+        Some('|') | Some('>') => chars.all(|c| c == '-' || c == '+' || c.is_ascii_digit()),
+        _ => false,
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+use failure::format_err;
+
+use super::Parameter;
+
+// ParameterError is the error of the `ParametersExt` accessors, naming the key at fault and, for
+// a value of the wrong type, describing the value, as in `string "42"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterError {
+    Missing(String),
+    WrongType {
+        key: String,
+        expected: &'static str,
+        got: String,
+    },
+}
+
+impl ParameterError {
+    // `in_section` qualifies the error with the dotted path of the section holding the map, as
+    // in `storage.s3.region is required`.
+    pub fn in_section(&self, section: &str) -> failure::Error {
+        match self {
+            ParameterError::Missing(key) => format_err!("{}.{} is required", section, key),
+            ParameterError::WrongType { key, expected, got } => {
+                format_err!("{}.{} must be {}, got {}", section, key, expected, got)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterError::Missing(key) => write!(f, "missing required parameter \"{}\"", key),
+            ParameterError::WrongType { key, expected, got } => {
+                write!(f, "parameter \"{}\" must be {}, got {}", key, expected, got)
+            }
+        }
+    }
+}
+
+impl error::Error for ParameterError {}
+
+// ParametersExt reads typed values out of a driver's parameter map. The `opt_*` accessors return
+// None for a missing key and for a yaml null alike; the `require_*` accessors fail on both.
+pub trait ParametersExt {
+    fn opt_str(&self, key: &str) -> Result<Option<&str>, ParameterError>;
+    fn opt_bool(&self, key: &str) -> Result<Option<bool>, ParameterError>;
+    fn opt_u32(&self, key: &str) -> Result<Option<u32>, ParameterError>;
+    fn opt_port(&self, key: &str) -> Result<Option<u16>, ParameterError>;
+
+    fn require_str(&self, key: &str) -> Result<&str, ParameterError> {
+        self.opt_str(key)?
+            .ok_or_else(|| ParameterError::Missing(key.to_string()))
+    }
+
+    fn require_bool(&self, key: &str) -> Result<bool, ParameterError> {
+        self.opt_bool(key)?
+            .ok_or_else(|| ParameterError::Missing(key.to_string()))
+    }
+}
+
+impl ParametersExt for BTreeMap<String, Parameter> {
+    fn opt_str(&self, key: &str) -> Result<Option<&str>, ParameterError> {
+        match self.get(key) {
+            None | Some(Parameter::Null) => Ok(None),
+            Some(Parameter::String(value)) => Ok(Some(value)),
+            Some(value) => Err(wrong_type(key, "a string", value)),
+        }
+    }
+
+    fn opt_bool(&self, key: &str) -> Result<Option<bool>, ParameterError> {
+        match self.get(key) {
+            None | Some(Parameter::Null) => Ok(None),
+            Some(Parameter::Boolean(value)) => Ok(Some(*value)),
+            Some(value) => Err(wrong_type(key, "a boolean", value)),
+        }
+    }
+
+    fn opt_u32(&self, key: &str) -> Result<Option<u32>, ParameterError> {
+        match self.get(key) {
+            None | Some(Parameter::Null) => Ok(None),
+            Some(Parameter::Integer(value))
+                if *value >= 0 && *value <= i64::from(u32::max_value()) =>
+            {
+                Ok(Some(*value as u32))
+            }
+            Some(value @ Parameter::Integer(_)) => {
+                Err(wrong_type(key, "an integer from 0 to 4294967295", value))
+            }
+            Some(value) => Err(wrong_type(key, "an integer", value)),
+        }
+    }
+
+    fn opt_port(&self, key: &str) -> Result<Option<u16>, ParameterError> {
+        match self.get(key) {
+            None | Some(Parameter::Null) => Ok(None),
+            Some(Parameter::Integer(value))
+                if *value > 0 && *value <= i64::from(u16::max_value()) =>
+            {
+                Ok(Some(*value as u16))
+            }
+            Some(value @ Parameter::Integer(_)) => Err(wrong_type(key, "a port number", value)),
+            Some(value) => Err(wrong_type(key, "an integer", value)),
+        }
+    }
+}
+
+fn wrong_type(key: &str, expected: &'static str, value: &Parameter) -> ParameterError {
+    ParameterError::WrongType {
+        key: key.to_string(),
+        expected,
+        got: describe(value),
+    }
+}
+
+// `describe` names the type of `value` along with the value itself, quoting strings so that a
+// quoted number reads as one.
+fn describe(value: &Parameter) -> String {
+    match value {
+        Parameter::Integer(value) => format!("integer {}", value),
+        Parameter::Double(value) => format!("number {}", value),
+        Parameter::String(value) => format!("string {:?}", value),
+        Parameter::Boolean(value) => format!("boolean {}", value),
+        Parameter::Null => "null".to_string(),
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::{Path, PathBuf};
+
+use failure::bail;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::include::{load, merge};
+use super::{parse_str, Configuration};
+
+// `parse_with_profile` parses the configuration file `base` with the overlay for `profile` merged
+// on top of it. The overlay sits next to `base`, named after it with the profile inserted before
+// the extension: `config.yaml` is overlaid by `config.production.yaml` for the `production`
+// profile. A missing overlay is not an error, the base is used on its own.
+pub fn parse_with_profile(base: &str, profile: &str) -> NianjiaResult<Configuration> {
+    let overlay = profile_path(Path::new(base), profile)?;
+    let mut document = load(Path::new(base))?;
+    if overlay.exists() {
+        merge(&mut document, load(&overlay)?);
+    }
+    parse_str(&serde_yaml::to_string(&document)?)
+}
+
+fn profile_path(base: &Path, profile: &str) -> NianjiaResult<PathBuf> {
+    if profile.is_empty() || profile.contains(|c| c == '/' || c == '\\' || c == '.') {
+        bail!("profile \"{}\" must be a plain name", profile);
+    }
+    let stem = base.file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+    let extension = base.extension().and_then(|ext| ext.to_str()).unwrap_or("yaml");
+    Ok(base.with_file_name(format!("{}.{}.{}", stem, profile, extension)))
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::Configuration;
+
+// The value secrets are replaced with in redacted output.
+pub const REDACTED: &str = "REDACTED";
+
+// The dotted yaml paths of the fields holding secrets. `*` matches any key of a mapping or any
+// element of a sequence. `http.tls.key` and the endpoint `clientkey` hold either a private key or
+// the path of its file, and are redacted either way. The `storage.*` paths cover the credentials
+// of the object store drivers, including those parsed as custom drivers.
+pub const SECRET_PATHS: &[&str] = &[
+    "storage.*.accesskey",
+    "storage.*.secretkey",
+    "storage.*.sessiontoken",
+    "storage.*.accountkey",
+    "storage.*.password",
+    "storage.*.accesskeysecret",
+    "storage.*.credentials.private_key",
+    "http.secret",
+    "http.tls.key",
+    "http.tls.keydata",
+    "redis.password",
+    "proxy.password",
+    "log.hooks.*.mail_options.smtp.password",
+    "reporting.bugsnag.apikey",
+    "reporting.newrelic.licensekey",
+    "notifications.endpoints.*.headers.*.*",
+    "notifications.endpoints.*.clientkey",
+];
+
+// `redact` replaces the non-empty scalars found at `paths` in `value` with `REDACTED`.
+pub(super) fn redact(value: &mut Value, paths: &[&str]) {
+    visit_set(value, paths, &mut |_, value| {
+        *value = Value::String(REDACTED.to_string())
+    });
+}
+
+// `set_paths` returns the non-empty scalars found at `paths` in `value`, along with their concrete
+// paths, e.g. `notifications.endpoints[0].headers.Authorization[0]`.
+pub(super) fn set_paths(value: &Value, paths: &[&str]) -> Vec<(String, Value)> {
+    let mut found = Vec::new();
+    visit_set(&mut value.clone(), paths, &mut |path, value| {
+        found.push((path.to_string(), value.clone()))
+    });
+    found
+}
+
+fn visit_set(value: &mut Value, paths: &[&str], f: &mut dyn FnMut(&str, &mut Value)) {
+    for path in paths {
+        let segments: Vec<&str> = path.split('.').collect();
+        visit_path(value, &segments, String::new(), f);
+    }
+}
+
+fn visit_path(
+    value: &mut Value,
+    segments: &[&str],
+    path: String,
+    f: &mut dyn FnMut(&str, &mut Value),
+) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            if is_set(value) {
+                f(&path, value);
+            }
+            return;
+        }
+    };
+    let key_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        Value::Mapping(map) if *segment == "*" => {
+            for (key, child) in map.iter_mut() {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    Value::Number(key) => key.to_string(),
+                    Value::Bool(key) => key.to_string(),
+                    _ => segment.to_string(),
+                };
+                visit_path(child, rest, key_path(&key), f);
+            }
+        }
+        Value::Mapping(map) => {
+            if let Some(child) = map.get_mut(&Value::String(segment.to_string())) {
+                visit_path(child, rest, key_path(segment), f);
+            }
+        }
+        Value::Sequence(items) if *segment == "*" => {
+            for (i, child) in items.iter_mut().enumerate() {
+                visit_path(child, rest, format!("{}[{}]", path, i), f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Bool(_) | Value::Number(_) => true,
+        Value::Sequence(_) | Value::Mapping(_) => false,
+    }
+}
+
+impl Configuration {
+    // `serialize_redacted` serializes the configuration to yaml with the `SECRET_PATHS` redacted.
+    pub fn serialize_redacted(&self) -> NianjiaResult<String> {
+        self.serialize_redacted_paths(SECRET_PATHS)
+    }
+
+    // `serialize_redacted_paths` serializes the configuration to yaml with the values at `paths`
+    // redacted.
+    pub fn serialize_redacted_paths(&self, paths: &[&str]) -> NianjiaResult<String> {
+        let mut value = serde_yaml::to_value(self)?;
+        redact(&mut value, paths);
+        Ok(serde_yaml::to_string(&value)?)
+    }
+}
+
+// Displaying a configuration gives its yaml form with the `SECRET_PATHS` redacted.
+impl fmt::Display for Configuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let yaml = self.serialize_redacted().map_err(|_| fmt::Error)?;
+        f.write_str(&yaml)
+    }
+}
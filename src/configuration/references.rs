@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+
+use failure::{bail, format_err, ResultExt};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::secrets::trim_newline;
+use super::{parse_file, Configuration};
+
+// SecretReference is a string parameter value of the form `scheme://path#key`, such as
+// `vault://secret/data/registry#redis_password` or
+// `awssm://arn:aws:secretsmanager:us-east-1:123456789012:secret:registry#redis_password`, which is
+// replaced at load time by the secret the provider registered for `scheme` returns. The `#key`
+// part is optional, and selects a field of a secret which holds several.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SecretReference {
+    pub scheme: String,
+    pub path: String,
+    pub key: Option<String>,
+}
+
+impl SecretReference {
+    // `parse` returns the reference `value` makes, or None if it isn't of the form
+    // `scheme://path`. Whether it is a secret reference depends on a provider handling `scheme`.
+    pub fn parse(value: &str) -> Option<SecretReference> {
+        let separator = value.find("://")?;
+        let scheme = &value[..separator];
+        let mut chars = scheme.chars();
+        if !chars.next().map_or(false, |c| c.is_ascii_lowercase())
+            || !chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+        {
+            return None;
+        }
+        let rest = &value[separator + 3..];
+        let (path, key) = match rest.rfind('#') {
+            Some(hash) => (&rest[..hash], Some(rest[hash + 1..].to_string())),
+            None => (rest, None),
+        };
+        if path.is_empty() {
+            return None;
+        }
+        Some(SecretReference {
+            scheme: scheme.to_string(),
+            path: path.to_string(),
+            key,
+        })
+    }
+}
+
+impl fmt::Display for SecretReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.path)?;
+        if let Some(key) = &self.key {
+            write!(f, "#{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+// The schemes of the secret providers the crate ships, along with the `secret://` references
+// `resolve_secrets` handles.
+pub const SECRET_REFERENCE_SCHEMES: &[&str] = &["secret", "env", "file", "vault", "awssm"];
+
+// `is_reference` tells whether `value` is a reference to one of the `SECRET_REFERENCE_SCHEMES`,
+// for the checks which skip secrets that are only known once resolved. Any other `scheme://`
+// value, such as a url, is checked as it is.
+pub(super) fn is_reference(value: &str) -> bool {
+    match SecretReference::parse(value) {
+        Some(reference) => SECRET_REFERENCE_SCHEMES.contains(&reference.scheme.as_str()),
+        None => false,
+    }
+}
+
+// SecretProvider resolves the references of one scheme. Providers are shared by the threads a
+// provider may spawn to resolve references concurrently, hence `Send` and `Sync`.
+pub trait SecretProvider: Send + Sync {
+    // `scheme` is the part of the references this provider resolves before `://`.
+    fn scheme(&self) -> &str;
+
+    fn resolve(&self, reference: &SecretReference) -> NianjiaResult<String>;
+
+    // `resolve_all` resolves every reference to this provider in a configuration, returning the
+    // results in the same order. The default resolves them one after the other; a provider
+    // backed by a remote service may override it to issue its requests concurrently, or to fetch
+    // a secret holding several keys only once.
+    fn resolve_all(&self, references: &[SecretReference]) -> Vec<NianjiaResult<String>> {
+        references.iter().map(|r| self.resolve(r)).collect()
+    }
+}
+
+// SecretProviders routes each reference to the provider registered for its scheme. A value whose
+// scheme no provider handles is left as it is.
+#[derive(Default)]
+pub struct SecretProviders {
+    providers: Vec<Box<dyn SecretProvider>>,
+}
+
+impl SecretProviders {
+    // `new` returns a set of providers resolving nothing.
+    pub fn new() -> SecretProviders {
+        SecretProviders::default()
+    }
+
+    // `with_defaults` returns the providers the crate always ships: `env://` and `file://`. The
+    // `vault://` and `awssm://` providers, built with the features of the same name, need an
+    // address or region and credentials, so they are registered by the caller.
+    pub fn with_defaults() -> SecretProviders {
+        SecretProviders::new()
+            .register(EnvSecrets)
+            .register(FileSecrets)
+    }
+
+    // `register` adds `provider`, replacing any provider registered for the same scheme.
+    pub fn register<P: SecretProvider + 'static>(mut self, provider: P) -> SecretProviders {
+        self.providers.retain(|p| p.scheme() != provider.scheme());
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    fn get(&self, scheme: &str) -> Option<&dyn SecretProvider> {
+        self.providers
+            .iter()
+            .find(|p| p.scheme() == scheme)
+            .map(|p| p.as_ref())
+    }
+}
+
+impl Configuration {
+    // `resolve_secret_references` replaces every string parameter holding a reference to a
+    // scheme `providers` handles with the secret it refers to. The references are collected
+    // first, so each provider gets all of its own at once.
+    pub fn resolve_secret_references(&mut self, providers: &SecretProviders) -> NianjiaResult<()> {
+        let mut pending: BTreeMap<String, Vec<(String, SecretReference)>> = BTreeMap::new();
+        self.resolve_parameters(&mut |key, value| {
+            if let Some(reference) = SecretReference::parse(value) {
+                if providers.get(&reference.scheme).is_some() {
+                    pending
+                        .entry(reference.scheme.clone())
+                        .or_default()
+                        .push((key.to_string(), reference));
+                }
+            }
+            Ok(None)
+        })?;
+
+        let mut resolved = BTreeMap::new();
+        for (scheme, references) in pending {
+            let provider = match providers.get(&scheme) {
+                Some(provider) => provider,
+                None => continue,
+            };
+            let batch: Vec<SecretReference> = references.iter().map(|(_, r)| r.clone()).collect();
+            let results = provider.resolve_all(&batch);
+            if results.len() != batch.len() {
+                bail!(
+                    "the {}:// secret provider resolved {} references out of {}",
+                    scheme,
+                    results.len(),
+                    batch.len()
+                );
+            }
+            for ((key, reference), result) in references.into_iter().zip(results) {
+                let secret = result.with_context(|e| {
+                    format!("failed to resolve {} for {}: {}", reference, key, e)
+                })?;
+                resolved.insert(key, secret);
+            }
+        }
+
+        self.resolve_parameters(&mut |key, _| Ok(resolved.remove(key)))
+    }
+}
+
+// `parse_file_with_secrets` parses `file` and resolves its secret references through
+// `providers`.
+pub fn parse_file_with_secrets(
+    file: &str,
+    providers: &SecretProviders,
+) -> NianjiaResult<Configuration> {
+    let mut config = parse_file(file)?;
+    config.resolve_secret_references(providers)?;
+    Ok(config)
+}
+
+// `select_key` returns the field `key` of `document`, a secret holding a yaml or json mapping,
+// or the whole document when the reference names no key.
+pub(super) fn select_key(document: &str, reference: &SecretReference) -> NianjiaResult<String> {
+    let key = match &reference.key {
+        Some(key) => key,
+        None => return Ok(document.to_string()),
+    };
+    let fields: BTreeMap<String, serde_yaml::Value> = serde_yaml::from_str(document)
+        .map_err(|e| format_err!("secret is not a mapping of keys to values: {}", e))?;
+    field(&fields, key)
+}
+
+// `field` returns the value of `key` in a secret's fields, as a string.
+pub(super) fn field(
+    fields: &BTreeMap<String, serde_yaml::Value>,
+    key: &str,
+) -> NianjiaResult<String> {
+    match fields.get(key) {
+        Some(serde_yaml::Value::String(value)) => Ok(value.clone()),
+        Some(serde_yaml::Value::Number(value)) => Ok(value.to_string()),
+        Some(serde_yaml::Value::Bool(value)) => Ok(value.to_string()),
+        Some(_) => bail!("secret key \"{}\" doesn't hold a string", key),
+        None => bail!("secret has no key \"{}\"", key),
+    }
+}
+
+// EnvSecrets resolves `env://NAME` from the environment variable `NAME`.
+#[derive(Debug, Default)]
+pub struct EnvSecrets;
+
+impl SecretProvider for EnvSecrets {
+    fn scheme(&self) -> &str {
+        "env"
+    }
+
+    fn resolve(&self, reference: &SecretReference) -> NianjiaResult<String> {
+        let value = env::var(&reference.path)
+            .map_err(|e| format_err!("environment variable {}: {}", reference.path, e))?;
+        select_key(&value, reference)
+    }
+}
+
+// FileSecrets resolves `file:///path` from the file at `/path`, less a single trailing newline.
+// With a `#key`, the file holds a yaml or json mapping the key is looked up in.
+#[derive(Debug, Default)]
+pub struct FileSecrets;
+
+impl SecretProvider for FileSecrets {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    fn resolve(&self, reference: &SecretReference) -> NianjiaResult<String> {
+        let content = fs::read_to_string(&reference.path)
+            .with_context(|e| format!("failed to read secret file {}: {}", reference.path, e))?;
+        select_key(&trim_newline(content), reference)
+    }
+}
@@ -0,0 +1,147 @@
+use failure::{bail, format_err};
+use url::Url;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::{parse_file, Configuration};
+
+// The largest configuration `parse_url` accepts over http(s), in bytes.
+pub const MAX_REMOTE_CONFIG_SIZE: u64 = 1024 * 1024;
+
+// How long `parse_url` waits on a config server, for connecting and for the whole response alike.
+pub const REMOTE_CONFIG_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// `parse_url` loads the configuration at `url`. A `file://` url is read with `parse_file`, includes
+// and all. An `http://` or `https://` url is fetched, when the crate is built with the
+// `remote-config` feature, and must answer with at most `MAX_REMOTE_CONFIG_SIZE` bytes of yaml or
+// json, as told by its content type or, failing that, the extension of its path. A server which
+// can't be reached, or answers with an error status, fails with an io error, so that
+// `ConfigError::from_load` reports it as `ConfigError::Io`, like a file which can't be read.
+pub fn parse_url(url: &str) -> NianjiaResult<Configuration> {
+    let parsed =
+        Url::parse(url).map_err(|e| format_err!("invalid config url \"{}\": {}", url, e))?;
+    match parsed.scheme() {
+        "file" => match parsed.to_file_path() {
+            Ok(path) => match path.to_str() {
+                Some(path) => parse_file(path),
+                None => bail!("config url \"{}\" is not a valid utf-8 path", url),
+            },
+            Err(()) => bail!("config url \"{}\" is not a local file path", url),
+        },
+        "http" | "https" => fetch(&parsed),
+        scheme => bail!(
+            "config url \"{}\" has unsupported scheme \"{}\", expected file, http or https",
+            url,
+            scheme
+        ),
+    }
+}
+
+#[cfg(feature = "remote-config")]
+fn fetch(url: &Url) -> NianjiaResult<Configuration> {
+    use std::io::Read;
+
+    let client = reqwest::Client::builder()
+        .timeout(REMOTE_CONFIG_TIMEOUT)
+        .build()?;
+    let response = client
+        .get(url.as_str())
+        .send()
+        .map_err(|e| fetch_error(format!("failed to fetch config from \"{}\": {}", url, e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(fetch_error(format!(
+            "failed to fetch config from \"{}\": server answered {}",
+            url, status
+        )));
+    }
+    if response
+        .content_length()
+        .map_or(false, |len| len > MAX_REMOTE_CONFIG_SIZE)
+    {
+        bail!(
+            "config at \"{}\" is larger than {} bytes",
+            url,
+            MAX_REMOTE_CONFIG_SIZE
+        );
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+        });
+    check_format(url, content_type.as_ref().map(String::as_str))?;
+
+    let mut content = String::new();
+    response
+        .take(MAX_REMOTE_CONFIG_SIZE + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| fetch_error(format!("failed to read config from \"{}\": {}", url, e)))?;
+    if content.len() as u64 > MAX_REMOTE_CONFIG_SIZE {
+        bail!(
+            "config at \"{}\" is larger than {} bytes",
+            url,
+            MAX_REMOTE_CONFIG_SIZE
+        );
+    }
+    super::parse_str(&content)
+}
+
+#[cfg(feature = "remote-config")]
+fn fetch_error(message: String) -> failure::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, message).into()
+}
+
+#[cfg(not(feature = "remote-config"))]
+fn fetch(url: &Url) -> NianjiaResult<Configuration> {
+    bail!(
+        "can't fetch config from \"{}\": this registry is built without the remote-config feature",
+        url
+    )
+}
+
+// The content types of documents `parse_str` reads: yaml, and json, which is yaml too.
+#[cfg(feature = "remote-config")]
+const CONFIG_CONTENT_TYPES: &[&str] = &[
+    "application/yaml",
+    "application/x-yaml",
+    "text/yaml",
+    "text/x-yaml",
+    "application/json",
+    "text/json",
+];
+
+// `check_format` accepts a response with a yaml or json content type. A generic or missing one,
+// as some servers give for any file, defers to the extension of the url path instead. An error
+// page served as html is rejected, rather than reported as a yaml syntax error.
+#[cfg(feature = "remote-config")]
+fn check_format(url: &Url, content_type: Option<&str>) -> NianjiaResult<()> {
+    match content_type {
+        Some(content_type) if CONFIG_CONTENT_TYPES.contains(&content_type) => return Ok(()),
+        None | Some("text/plain") | Some("application/octet-stream") => {}
+        Some(content_type) => bail!(
+            "config at \"{}\" has content type {}, expected yaml or json",
+            url,
+            content_type
+        ),
+    }
+    let path = url.path().to_ascii_lowercase();
+    if [".yaml", ".yml", ".json"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+    {
+        Ok(())
+    } else {
+        bail!(
+            "config at \"{}\" has no yaml or json content type or extension",
+            url
+        )
+    }
+}
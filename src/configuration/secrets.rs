@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use failure::{format_err, ResultExt};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::{parse_file, Configuration, Parameter, StorageMedia};
+
+// A string parameter of the form `secret://name` is replaced at load time by the value the
+// configured CredentialsProvider returns for `name`.
+pub const SECRET_SCHEME: &str = "secret://";
+
+// CredentialsProvider looks up secrets referenced from the configuration by name.
+pub trait CredentialsProvider {
+    fn resolve(&self, name: &str) -> NianjiaResult<String>;
+}
+
+impl<F> CredentialsProvider for F
+where
+    F: Fn(&str) -> NianjiaResult<String>,
+{
+    fn resolve(&self, name: &str) -> NianjiaResult<String> {
+        self(name)
+    }
+}
+
+// EnvCredentials resolves `secret://name` from the environment variable `name`.
+#[derive(Debug, Default)]
+pub struct EnvCredentials;
+
+impl CredentialsProvider for EnvCredentials {
+    fn resolve(&self, name: &str) -> NianjiaResult<String> {
+        env::var(name).map_err(|e| format_err!("environment variable {}: {}", name, e))
+    }
+}
+
+// FileCredentials resolves `secret://name` from the file `name` in `dir`, as mounted by docker
+// and kubernetes secrets. A single trailing newline is stripped.
+#[derive(Debug)]
+pub struct FileCredentials {
+    dir: PathBuf,
+}
+
+impl FileCredentials {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> FileCredentials {
+        FileCredentials { dir: dir.into() }
+    }
+}
+
+impl CredentialsProvider for FileCredentials {
+    fn resolve(&self, name: &str) -> NianjiaResult<String> {
+        let path = self.dir.join(name);
+        let secret = fs::read_to_string(&path)
+            .with_context(|e| format!("failed to read secret file {}: {}", path.display(), e))?;
+        Ok(trim_newline(secret))
+    }
+}
+
+// `trim_newline` strips the single trailing newline editors and `echo` leave in secret files.
+pub(super) fn trim_newline(mut secret: String) -> String {
+    if secret.ends_with('\n') {
+        secret.pop();
+        if secret.ends_with('\r') {
+            secret.pop();
+        }
+    }
+    secret
+}
+
+impl Configuration {
+    // `resolve_secrets` replaces every `secret://name` parameter value with the secret returned
+    // by `provider`.
+    pub fn resolve_secrets(&mut self, provider: &dyn CredentialsProvider) -> NianjiaResult<()> {
+        self.resolve_parameters(&mut |key, value| {
+            if !value.starts_with(SECRET_SCHEME) {
+                return Ok(None);
+            }
+            let name = &value[SECRET_SCHEME.len()..];
+            let secret = provider.resolve(name).with_context(|e| {
+                format!("failed to resolve {}{} for {}: {}", SECRET_SCHEME, name, key, e)
+            })?;
+            Ok(Some(secret))
+        })
+    }
+
+    // `resolve_parameters` calls `resolve` with the dotted key and value of every string
+    // parameter, and of the typed fields holding secrets, replacing the value with the one
+    // `resolve` returns, if any.
+    pub(super) fn resolve_parameters(
+        &mut self,
+        resolve: &mut dyn FnMut(&str, &str) -> NianjiaResult<Option<String>>,
+    ) -> NianjiaResult<()> {
+        let storage = &mut self.storage;
+        match &mut storage.media {
+            StorageMedia::Filesystem(map) => resolve_map(map, "storage.filesystem", resolve)?,
+            StorageMedia::S3(map) => resolve_map(map, "storage.s3", resolve)?,
+            StorageMedia::InMemory => {}
+            StorageMedia::Custom(name, map) => {
+                resolve_map(map, &format!("storage.{}", name), resolve)?
+            }
+        }
+        if let Some(maintenance) = &mut storage.maintenance {
+            resolve_map(
+                &mut maintenance.uploadpurging.parameters,
+                "storage.maintenance.uploadpurging",
+                resolve,
+            )?;
+            resolve_map(
+                &mut maintenance.readonly.parameters,
+                "storage.maintenance.readonly",
+                resolve,
+            )?;
+        }
+        if let Some(cache) = &mut storage.cache {
+            resolve_map(&mut (cache.0).parameters, "storage.cache", resolve)?;
+        }
+        if let Some(delete) = &mut storage.delete {
+            resolve_map(&mut (delete.0).parameters, "storage.delete", resolve)?;
+        }
+        if let Some(redirect) = &mut storage.redirect {
+            resolve_map(&mut (redirect.0).parameters, "storage.redirect", resolve)?;
+        }
+        for (name, parameters) in self.auth.iter_mut() {
+            let prefix = format!("auth.{}", name);
+            resolve_map(&mut parameters.parameters, &prefix, resolve)?;
+        }
+        for (subsystem, middlewares) in self.middleware.iter_mut() {
+            for (i, middleware) in middlewares.iter_mut().enumerate() {
+                let prefix = format!("middleware.{}[{}].options", subsystem, i);
+                resolve_map(&mut middleware.options.parameters, &prefix, resolve)?;
+            }
+        }
+
+        resolve_field(&mut self.http.secret, "http.secret", resolve)?;
+        if let Some(redis) = &mut self.redis {
+            resolve_field(&mut redis.password, "redis.password", resolve)?;
+        }
+        if let Some(proxy) = &mut self.proxy {
+            resolve_field(&mut proxy.password, "proxy.password", resolve)?;
+        }
+        for (i, hook) in self.log.hooks.iter_mut().enumerate() {
+            let key = format!("log.hooks[{}].mail_options.smtp.password", i);
+            resolve_field(&mut hook.mail_options.smtp.password, &key, resolve)?;
+        }
+        let reporting = &mut self.reporting;
+        let api_key = &mut reporting.bugsnag.API_key;
+        resolve_field(api_key, "reporting.bugsnag.apikey", resolve)?;
+        let license_key = &mut reporting.new_relic.license_key;
+        resolve_field(license_key, "reporting.newrelic.licensekey", resolve)?;
+        for (i, endpoint) in self.notifications.endpoints.iter_mut().enumerate() {
+            for (name, values) in endpoint.headers.iter_mut() {
+                for (j, value) in values.iter_mut().enumerate() {
+                    let key = format!("notifications.endpoints[{}].headers.{}[{}]", i, name, j);
+                    resolve_field(value, &key, resolve)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn resolve_field(
+    value: &mut String,
+    key: &str,
+    resolve: &mut dyn FnMut(&str, &str) -> NianjiaResult<Option<String>>,
+) -> NianjiaResult<()> {
+    if let Some(resolved) = resolve(key, value)? {
+        *value = resolved;
+    }
+    Ok(())
+}
+
+fn resolve_map(
+    map: &mut BTreeMap<String, Parameter>,
+    prefix: &str,
+    resolve: &mut dyn FnMut(&str, &str) -> NianjiaResult<Option<String>>,
+) -> NianjiaResult<()> {
+    for (key, value) in map.iter_mut() {
+        if let Parameter::String(value) = value {
+            resolve_field(value, &format!("{}.{}", prefix, key), resolve)?;
+        }
+    }
+    Ok(())
+}
+
+// `parse_file_with_credentials` parses `file` and resolves its `secret://` references through
+// `provider`.
+pub fn parse_file_with_credentials(
+    file: &str,
+    provider: &dyn CredentialsProvider,
+) -> NianjiaResult<Configuration> {
+    let mut config = parse_file(file)?;
+    config.resolve_secrets(provider)?;
+    Ok(config)
+}
@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::defaults::{fill, fill_interval};
+use super::references::is_reference;
+use super::validation::{
+    check_max_duration, check_min_duration, check_pem, is_identifier, require, resolve,
+    validate_health_durations, EndpointTlsIssue, PemKind, ValidationErrors, MAX_REDIS_TIMEOUT,
+    MIN_HTTP_READ_TIMEOUT, NEW_RELIC_LICENSE_KEY_LEN,
+};
+use super::{
+    bare_hostname, BackoffStrategy, Configuration, Duration, Health, Http, Log, LogOutput,
+    Maintenance, Notifications, Policy, Proxy, Redis, Reporting, Storage, Validation,
+    DEFAULT_HEALTH_STATUS_CODE, DEFAULT_HTTP_NET, DEFAULT_LOG_LEVEL, DEFAULT_PROMETHEUS_PATH,
+    KNOWN_ACTIONS,
+};
+
+// SectionContext is what the checks of a section need besides the section itself.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SectionContext<'a> {
+    // `root` is the directory the paths the configuration references are resolved under, as
+    // `validate_paths_relative_to` does, or None to resolve them on this host.
+    pub root: Option<&'a Path>,
+}
+
+// ConfigSection is a top-level section of the configuration which validates and defaults
+// itself, so that it can be checked in isolation. Checks which span sections, such as a cache
+// requiring redis, are left to `Configuration`.
+pub trait ConfigSection {
+    fn validate(&self, ctx: &SectionContext) -> ValidationErrors;
+
+    // `apply_defaults` writes out the defaults of the settings left empty or zero. Applying them
+    // again changes nothing.
+    fn apply_defaults(&mut self) {}
+}
+
+// An optional section which is absent has nothing to check or default.
+impl<T: ConfigSection> ConfigSection for Option<T> {
+    fn validate(&self, ctx: &SectionContext) -> ValidationErrors {
+        match self {
+            Some(section) => section.validate(ctx),
+            None => ValidationErrors::default(),
+        }
+    }
+
+    fn apply_defaults(&mut self) {
+        if let Some(section) = self {
+            section.apply_defaults();
+        }
+    }
+}
+
+impl Configuration {
+    // `sections` lists the sections, in the order `validate` reports their errors. `auth` and
+    // `middleware` are plain maps rather than sections: they are checked along with `authorder`
+    // and the other checks spanning sections.
+    pub(super) fn sections(&self) -> Vec<&dyn ConfigSection> {
+        vec![
+            &self.log as &dyn ConfigSection,
+            &self.http,
+            &self.notifications,
+            &self.health,
+            &self.redis,
+            &self.proxy,
+            &self.reporting,
+            &self.policy,
+            &self.storage,
+            &self.validation,
+        ]
+    }
+
+    // `sections_mut` lists the sections, for `apply_defaults_in_place`.
+    pub(super) fn sections_mut(&mut self) -> Vec<&mut dyn ConfigSection> {
+        vec![
+            &mut self.log as &mut dyn ConfigSection,
+            &mut self.http,
+            &mut self.notifications,
+            &mut self.health,
+            &mut self.redis,
+            &mut self.proxy,
+            &mut self.reporting,
+            &mut self.policy,
+            &mut self.storage,
+            &mut self.validation,
+        ]
+    }
+}
+
+impl ConfigSection for Log {
+    fn validate(&self, ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        if let LogOutput::File(path) = self.output() {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let dir = resolve(ctx.root, dir);
+            if !dir.is_dir() {
+                errors.push(format!(
+                    "log.output.file {}: directory {} does not exist",
+                    path.display(),
+                    dir.display()
+                ));
+            }
+        }
+        for (i, hook) in self.hooks.iter().enumerate() {
+            if hook.disabled {
+                continue;
+            }
+            let prefix = format!("log.hooks[{}]", i);
+            require(&mut errors, &prefix, "type", &hook._type);
+            if hook._type == "mail" {
+                let options = &hook.mail_options;
+                require(
+                    &mut errors,
+                    &prefix,
+                    "mail_options.smtp.addr",
+                    &options.smtp.addr,
+                );
+                require(&mut errors, &prefix, "mail_options.from", &options.from);
+                if options.to.is_empty() {
+                    errors.push(format!("{}.mail_options.to is required", prefix));
+                }
+            }
+        }
+        errors
+    }
+
+    fn apply_defaults(&mut self) {
+        fill(&mut self.level, DEFAULT_LOG_LEVEL);
+    }
+}
+
+impl ConfigSection for Http {
+    fn validate(&self, ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        if !self.host.is_empty() && bare_hostname(&self.host) != self.host {
+            errors.push(format!(
+                "http.host \"{}\" must be a bare hostname",
+                self.host
+            ));
+        }
+        if let Some(timeout) = &self.read_timeout {
+            check_min_duration(
+                &mut errors,
+                "http.readtimeout",
+                timeout,
+                MIN_HTTP_READ_TIMEOUT,
+            );
+        }
+        let timeouts = [
+            ("http.writetimeout", &self.write_timeout),
+            ("http.idletimeout", &self.idle_timeout),
+        ];
+        for (key, timeout) in &timeouts {
+            if timeout.as_ref().map_or(false, Duration::is_zero) {
+                errors.push(format!("{} must be positive", key));
+            }
+        }
+        if !self.http2.disabled && self.http2.max_concurrent_streams == Some(0) {
+            errors.push("http.http2.maxconcurrentstreams must be positive".to_string());
+        }
+        for (i, ca) in self.tls.client_CAs.iter().enumerate() {
+            let key = format!("http.tls.clientcas[{}] \"{}\"", i, ca);
+            match fs::read(resolve(ctx.root, Path::new(ca))) {
+                Err(e) => errors.push(format!("{}: {}", key, e)),
+                Ok(bytes) => {
+                    let pems = pem::parse_many(&bytes);
+                    if pems.is_empty() || pems.iter().any(|pem| pem.tag != "CERTIFICATE") {
+                        errors.push(format!("{} holds no pem certificate", key));
+                    }
+                }
+            }
+        }
+        if self.tls.has_conflicting_modes() {
+            errors.push(
+                "http.tls: configure either a manual certificate or letsencrypt, not both"
+                    .to_string(),
+            );
+        }
+        errors
+    }
+
+    fn apply_defaults(&mut self) {
+        fill(&mut self.net, DEFAULT_HTTP_NET);
+        fill(&mut self.debug.prometheus.path, DEFAULT_PROMETHEUS_PATH);
+    }
+}
+
+impl ConfigSection for Notifications {
+    fn validate(&self, ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        for (i, endpoint) in self.active_endpoints() {
+            let prefix = format!("notifications.endpoints[{}]", i);
+            require(&mut errors, &prefix, "name", &endpoint.name);
+            require(&mut errors, &prefix, "url", &endpoint.url);
+        }
+
+        // Endpoint names label the notification metrics, so two active endpoints can't share
+        // one. The check is a single pass over a set, linear in the number of endpoints, so
+        // configs which fan out to thousands of endpoints validate as quickly as small ones.
+        let mut seen = HashSet::new();
+        for (i, endpoint) in self.active_endpoints() {
+            let name = endpoint.name.as_str();
+            if !name.is_empty() && !seen.insert(name) {
+                errors.push(format!(
+                    "notifications.endpoints[{}].name \"{}\" is used by an earlier endpoint",
+                    i, name
+                ));
+            }
+        }
+
+        // Notification headers usually carry credentials, so an empty value is a templating
+        // mistake.
+        for (i, endpoint) in self.active_endpoints() {
+            for (name, values) in &endpoint.headers {
+                if values.is_empty() || values.iter().any(|value| value.trim().is_empty()) {
+                    errors.push(format!(
+                        "notifications.endpoints[{}].headers[\"{}\"] has an empty value",
+                        i, name
+                    ));
+                }
+            }
+        }
+
+        for (i, endpoint) in self.active_endpoints() {
+            let prefix = format!("notifications.endpoints[{}]", i);
+            let (cert, key) = (&endpoint.client_cert, &endpoint.client_key);
+            if cert.is_empty() != key.is_empty() {
+                errors.push(format!(
+                    "{}.clientcert and {}.clientkey must be set together",
+                    prefix, prefix
+                ));
+                continue;
+            }
+            let cert_key = format!("{}.clientcert", prefix);
+            check_pem(&mut errors, ctx.root, &cert_key, cert, PemKind::Certificate);
+            let key_key = format!("{}.clientkey", prefix);
+            check_pem(&mut errors, ctx.root, &key_key, key, PemKind::PrivateKey);
+        }
+        for issue in self.validate_endpoint_reachability_config() {
+            if let EndpointTlsIssue::Error(error) = issue {
+                errors.push(error);
+            }
+        }
+
+        // `ignore.actions` predates the check and was never restricted, so only `actions` is.
+        for (i, endpoint) in self.active_endpoints() {
+            for action in &endpoint.actions {
+                if !KNOWN_ACTIONS.contains(&action.as_str()) {
+                    errors.push(format!(
+                        "notifications.endpoints[{}].actions contains unknown action \"{}\", \
+                         expected one of {}",
+                        i,
+                        action,
+                        KNOWN_ACTIONS.join(", ")
+                    ));
+                }
+            }
+        }
+
+        if self.max_retries == Some(0) {
+            errors.push("notifications.maxretries must be positive".to_string());
+        }
+        if self.max_events == Some(0) {
+            errors.push("notifications.maxevents must be positive".to_string());
+        }
+
+        for (i, endpoint) in self.active_endpoints() {
+            if endpoint.backoff_strategy != BackoffStrategy::Exponential {
+                continue;
+            }
+            let prefix = format!("notifications.endpoints[{}]", i);
+            match &endpoint.max_backoff {
+                None => errors.push(format!(
+                    "{}.maxbackoff is required with backoffstrategy exponential",
+                    prefix
+                )),
+                Some(max) if max.as_std() < endpoint.backoff.as_std() => errors.push(format!(
+                    "{}.maxbackoff {} is below the backoff of {}",
+                    prefix, max, endpoint.backoff
+                )),
+                Some(_) => {}
+            }
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Health {
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        for (i, checker) in self.file_checkers.iter().enumerate() {
+            require(
+                &mut errors,
+                &format!("health.file[{}]", i),
+                "file",
+                &checker.file,
+            );
+        }
+        for (i, checker) in self.http_checkers.iter().enumerate() {
+            require(
+                &mut errors,
+                &format!("health.http[{}]", i),
+                "uri",
+                &checker.url,
+            );
+        }
+        for (i, checker) in self.tcp_checkers.iter().enumerate() {
+            require(
+                &mut errors,
+                &format!("health.tcp[{}]", i),
+                "addr",
+                &checker.addr,
+            );
+        }
+        validate_health_durations(&mut errors, self);
+        errors
+    }
+
+    fn apply_defaults(&mut self) {
+        for checker in &mut self.http_checkers {
+            if checker.status_code == 0 {
+                checker.status_code = DEFAULT_HEALTH_STATUS_CODE;
+            }
+            fill_interval(&mut checker.interval);
+        }
+        for checker in &mut self.file_checkers {
+            fill_interval(&mut checker.interval);
+        }
+        for checker in &mut self.tcp_checkers {
+            fill_interval(&mut checker.interval);
+        }
+        let storage_driver = &mut self.stroage_driver;
+        if let Some((interval, threshold)) = storage_driver.schedule() {
+            storage_driver.interval = interval;
+            storage_driver.threshold = threshold;
+        }
+    }
+}
+
+impl ConfigSection for Redis {
+    // A maxactive of 0 leaves the number of connections unlimited, so any maxidle fits under it.
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        let timeouts = [
+            ("redis.dialtimeout", &self.dial_timeout),
+            ("redis.readtimeout", &self.read_timeout),
+            ("redis.writetimeout", &self.write_timeout),
+        ];
+        for (key, timeout) in &timeouts {
+            check_max_duration(&mut errors, key, timeout, MAX_REDIS_TIMEOUT);
+        }
+        let pool = &self.pool;
+        if pool.max_active != 0 && pool.max_idle > pool.max_active {
+            errors.push(format!(
+                "redis.pool.maxidle ({}) cannot exceed maxactive ({})",
+                pool.max_idle, pool.max_active
+            ));
+        }
+        if pool.max_idle > 0 && pool.idle_timeout.is_zero() {
+            errors.push(
+                "redis.pool.idletimeout must be non-zero when redis.pool.maxidle is set"
+                    .to_string(),
+            );
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Proxy {
+    // Credentials for the upstream are sent with basic auth, which mustn't go over cleartext.
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        if self.username.is_empty() != self.password.is_empty() {
+            errors.push("proxy.username and proxy.password must be provided together".to_string());
+        } else if !self.username.is_empty()
+            && self.enabled()
+            && !self.remote_url.to_ascii_lowercase().starts_with("https://")
+        {
+            errors.push(format!(
+                "proxy.remoteurl \"{}\" must use https when proxy.username and proxy.password \
+                 are set",
+                self.remote_url
+            ));
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Reporting {
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        let new_relic = &self.new_relic;
+        if !new_relic.is_enabled() {
+            return errors;
+        }
+        if new_relic.name.is_empty() {
+            errors.push(
+                "reporting.newrelic.name is required when reporting.newrelic.licensekey is set"
+                    .to_string(),
+            );
+        }
+        // A reference to a secret is checked once it is resolved.
+        let key = &new_relic.license_key;
+        if !is_reference(key)
+            && (key.len() != NEW_RELIC_LICENSE_KEY_LEN
+                || !key.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            errors.push(format!(
+                "reporting.newrelic.licensekey must be {} letters and digits",
+                NEW_RELIC_LICENSE_KEY_LEN
+            ));
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Policy {
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        let mut seen = HashSet::new();
+        for class in self.allowed_classes() {
+            if !is_identifier(class) {
+                errors.push(format!(
+                    "policy.repository.classes contains invalid class \"{}\"",
+                    class
+                ));
+            } else if !seen.insert(class.as_str()) {
+                errors.push(format!(
+                    "policy.repository.classes contains duplicate \"{}\"",
+                    class
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Storage {
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = self.media.check_parameters() {
+            errors.push(e.to_string());
+        }
+        if let Some(Err(e)) = self.maintenance.as_ref().map(Maintenance::read_only) {
+            errors.push(e.to_string());
+        }
+        errors
+    }
+}
+
+impl ConfigSection for Validation {
+    fn validate(&self, _ctx: &SectionContext) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        if let Err(e) = self.manifests.urls.regexes() {
+            errors.push(e.to_string());
+        }
+        errors
+    }
+}
@@ -0,0 +1,74 @@
+use semver::Version;
+use serde_yaml::Value;
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::Configuration;
+
+// The version of the first release, which understands every setting not listed in
+// `INTRODUCED_IN`.
+pub const BASELINE_VERSION: (u64, u64, u64) = (0, 0, 1);
+
+// The registry versions which introduced settings, by dotted yaml path. `*` matches any key of a
+// mapping or any element of a sequence. A setting added to the configuration is listed here with
+// the version of the release which first understands it, in the same change.
+pub const INTRODUCED_IN: &[(&str, (u64, u64, u64))] = &[
+    ("instancename", (0, 0, 2)),
+    ("authorder", (0, 0, 2)),
+    ("features", (0, 0, 2)),
+    ("log.output", (0, 0, 2)),
+    ("log.reportcaller", (0, 0, 2)),
+    ("http.readtimeout", (0, 0, 2)),
+    ("http.writetimeout", (0, 0, 2)),
+    ("http.idletimeout", (0, 0, 2)),
+    ("http.maxrequestbody", (0, 0, 2)),
+    ("http.tls.certificatedata", (0, 0, 2)),
+    ("http.tls.keydata", (0, 0, 2)),
+    ("http.http2.maxconcurrentstreams", (0, 0, 2)),
+    ("notifications.maxretries", (0, 0, 2)),
+    ("notifications.maxevents", (0, 0, 2)),
+    ("notifications.overflow", (0, 0, 2)),
+    ("notifications.endpoints.*.backoffstrategy", (0, 0, 2)),
+    ("notifications.endpoints.*.maxbackoff", (0, 0, 2)),
+    ("notifications.endpoints.*.actions", (0, 0, 2)),
+    ("notifications.endpoints.*.clientcert", (0, 0, 2)),
+    ("notifications.endpoints.*.clientkey", (0, 0, 2)),
+    ("notifications.endpoints.*.insecureskipverify", (0, 0, 2)),
+    ("compatibility.manifesturls", (0, 0, 2)),
+];
+
+impl Configuration {
+    // `requires_min_version` returns the oldest registry version which understands every setting
+    // this configuration uses. Only settings which deviate from their defaults count, since an
+    // older registry behaves the same without them.
+    pub fn requires_min_version(&self) -> NianjiaResult<Version> {
+        let value = self.minimal_value()?;
+        let (major, minor, patch) = BASELINE_VERSION;
+        let mut required = Version::new(major, minor, patch);
+        for (path, (major, minor, patch)) in INTRODUCED_IN {
+            let segments: Vec<&str> = path.split('.').collect();
+            let since = Version::new(*major, *minor, *patch);
+            if since > required && is_present(&value, &segments) {
+                required = since;
+            }
+        }
+        Ok(required)
+    }
+}
+
+fn is_present(value: &Value, segments: &[&str]) -> bool {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return true,
+    };
+    match value {
+        Value::Mapping(map) if *segment == "*" => map.values().any(|child| is_present(child, rest)),
+        Value::Mapping(map) => map
+            .get(&Value::String(segment.to_string()))
+            .map_or(false, |child| is_present(child, rest)),
+        Value::Sequence(items) if *segment == "*" => {
+            items.iter().any(|child| is_present(child, rest))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use failure::{bail, format_err, Error};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::parameters::{ParameterError, ParametersExt};
+use super::{Configuration, Maintenance, Parameter, StorageMedia};
+
+// S3Config is the typed form of the `storage.s3` parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Config {
+    pub region: String,
+    pub bucket: String,
+    pub rootdirectory: String,
+    pub encrypt: bool,
+    pub secure: bool,
+    pub accesskey: String,
+    pub secretkey: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub regionendpoint: Option<String>,
+    // `extra` keeps the parameters this struct doesn't model, for the driver to interpret.
+    pub extra: BTreeMap<String, Parameter>,
+}
+
+const S3_KEYS: &[&str] = &[
+    "region",
+    "bucket",
+    "rootdirectory",
+    "encrypt",
+    "secure",
+    "accesskey",
+    "secretkey",
+    "host",
+    "port",
+    "regionendpoint",
+];
+
+impl<'a> TryFrom<&'a BTreeMap<String, Parameter>> for S3Config {
+    type Error = Error;
+
+    fn try_from(map: &'a BTreeMap<String, Parameter>) -> Result<S3Config, Error> {
+        let qualify = |e: ParameterError| e.in_section("storage.s3");
+        Ok(S3Config {
+            region: map.require_str("region").map_err(qualify)?.to_string(),
+            bucket: map.require_str("bucket").map_err(qualify)?.to_string(),
+            rootdirectory: map
+                .opt_str("rootdirectory")
+                .map_err(qualify)?
+                .map(str::to_string)
+                .unwrap_or_default(),
+            encrypt: map.opt_bool("encrypt").map_err(qualify)?.unwrap_or(false),
+            secure: map.opt_bool("secure").map_err(qualify)?.unwrap_or(true),
+            accesskey: map
+                .opt_str("accesskey")
+                .map_err(qualify)?
+                .map(str::to_string)
+                .unwrap_or_default(),
+            secretkey: map
+                .opt_str("secretkey")
+                .map_err(qualify)?
+                .map(str::to_string)
+                .unwrap_or_default(),
+            host: map.opt_str("host").map_err(qualify)?.map(str::to_string),
+            port: map.opt_port("port").map_err(qualify)?,
+            regionendpoint: map
+                .opt_str("regionendpoint")
+                .map_err(qualify)?
+                .map(str::to_string),
+            extra: map
+                .iter()
+                .filter(|(key, _)| !S3_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        })
+    }
+}
+
+// The root directory the filesystem driver uses when `rootdirectory` is unset.
+pub const DEFAULT_FILESYSTEM_ROOT: &str = "/var/lib/registry";
+
+// The bounds of `storage.filesystem.maxthreads`, the number of threads the filesystem driver may
+// block in file operations at once.
+pub const MIN_FILESYSTEM_THREADS: u32 = 1;
+pub const MAX_FILESYSTEM_THREADS: u32 = 1000;
+
+// FilesystemConfig is the typed form of the `storage.filesystem` parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilesystemConfig {
+    pub rootdirectory: String,
+    // `maxthreads` is unset when the driver picks its own default.
+    pub maxthreads: Option<u32>,
+}
+
+impl<'a> TryFrom<&'a BTreeMap<String, Parameter>> for FilesystemConfig {
+    type Error = Error;
+
+    fn try_from(map: &'a BTreeMap<String, Parameter>) -> Result<FilesystemConfig, Error> {
+        let qualify = |e: ParameterError| e.in_section("storage.filesystem");
+        let maxthreads = map.opt_u32("maxthreads").map_err(qualify)?;
+        let bounds = MIN_FILESYSTEM_THREADS..=MAX_FILESYSTEM_THREADS;
+        if maxthreads.map_or(false, |threads| !bounds.contains(&threads)) {
+            return Err(format_err!(
+                "storage.filesystem.maxthreads must be between {} and {}",
+                MIN_FILESYSTEM_THREADS,
+                MAX_FILESYSTEM_THREADS
+            ));
+        }
+        Ok(FilesystemConfig {
+            rootdirectory: map
+                .opt_str("rootdirectory")
+                .map_err(qualify)?
+                .map(str::to_string)
+                .unwrap_or_else(|| DEFAULT_FILESYSTEM_ROOT.to_string()),
+            maxthreads,
+        })
+    }
+}
+
+impl FilesystemConfig {
+    // `validate` checks that `rootdirectory` is an absolute path to a directory which exists, or
+    // which can be created under its closest existing ancestor.
+    pub fn validate(&self) -> NianjiaResult<()> {
+        let root = Path::new(&self.rootdirectory);
+        if !root.is_absolute() {
+            return Err(format_err!(
+                "storage.filesystem.rootdirectory \"{}\" must be an absolute path",
+                self.rootdirectory
+            ));
+        }
+        let existing = match root.ancestors().find(|path| path.exists()) {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        let metadata = fs::metadata(existing)?;
+        if !metadata.is_dir() {
+            return Err(format_err!(
+                "storage.filesystem.rootdirectory \"{}\": {} is not a directory",
+                self.rootdirectory,
+                existing.display()
+            ));
+        }
+        if existing != root && metadata.permissions().readonly() {
+            return Err(format_err!(
+                "storage.filesystem.rootdirectory \"{}\" can't be created, {} is read-only",
+                self.rootdirectory,
+                existing.display()
+            ));
+        }
+        Ok(())
+    }
+}
+
+// ReadOnly is the typed form of the `storage.maintenance.readonly` parameters. `text` is the
+// message returned to clients whose writes are refused while `enabled`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadOnly {
+    pub enabled: bool,
+    pub text: Option<String>,
+}
+
+impl<'a> TryFrom<&'a BTreeMap<String, Parameter>> for ReadOnly {
+    type Error = Error;
+
+    fn try_from(map: &'a BTreeMap<String, Parameter>) -> Result<ReadOnly, Error> {
+        let qualify = |e: ParameterError| e.in_section("storage.maintenance.readonly");
+        Ok(ReadOnly {
+            enabled: map.opt_bool("enabled").map_err(qualify)?.unwrap_or(false),
+            text: map
+                .opt_str("text")
+                .map_err(qualify)?
+                .map(str::to_string)
+                .filter(|text| !text.is_empty()),
+        })
+    }
+}
+
+impl Maintenance {
+    // `read_only` returns the typed read-only mode parameters.
+    pub fn read_only(&self) -> NianjiaResult<ReadOnly> {
+        ReadOnly::try_from(&self.readonly.parameters)
+    }
+}
+
+impl Configuration {
+    // `filesystem_config` returns the validated filesystem parameters when filesystem is the
+    // active storage driver.
+    pub fn filesystem_config(&self) -> Option<NianjiaResult<FilesystemConfig>> {
+        match &self.storage.media {
+            StorageMedia::Filesystem(map) => Some(
+                FilesystemConfig::try_from(map)
+                    .and_then(|config| config.validate().map(|()| config)),
+            ),
+            _ => None,
+        }
+    }
+
+    // `s3_config` returns the typed s3 parameters when s3 is the active storage driver.
+    pub fn s3_config(&self) -> Option<NianjiaResult<S3Config>> {
+        match &self.storage.media {
+            StorageMedia::S3(map) => Some(S3Config::try_from(map)),
+            _ => None,
+        }
+    }
+}
+
+impl StorageMedia {
+    // `check_parameters` checks that the parameters of the storage driver have the types its
+    // typed form expects, without looking at the filesystem.
+    pub(super) fn check_parameters(&self) -> NianjiaResult<()> {
+        match self {
+            StorageMedia::S3(map) => S3Config::try_from(map).map(|_| ()),
+            StorageMedia::Filesystem(map) => FilesystemConfig::try_from(map).map(|_| ()),
+            StorageMedia::InMemory => Ok(()),
+            // Drivers the registry has no typed form for are only checked for having parameters.
+            StorageMedia::Custom(name, map) if map.is_empty() => {
+                bail!("storage.{} requires parameters", name)
+            }
+            StorageMedia::Custom(..) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+use super::Configuration;
+
+// ConfigSummary describes which subsystems a configuration enables, for logging at startup.
+#[derive(Debug, PartialEq)]
+pub struct ConfigSummary {
+    // `storage` is the name of the active storage driver.
+    pub storage: String,
+    // `auth` lists the configured auth backends.
+    pub auth: Vec<String>,
+    // `notification_endpoints` counts the notification endpoints which are not disabled.
+    pub notification_endpoints: usize,
+    // `health_checkers` counts the configured health checkers, including the storage driver
+    // checker when it is enabled.
+    pub health_checkers: usize,
+    pub proxy: bool,
+    pub redis: bool,
+    pub tls: bool,
+}
+
+impl fmt::Display for ConfigSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let auth = if self.auth.is_empty() {
+            "none".to_string()
+        } else {
+            self.auth.join("+")
+        };
+        write!(
+            f,
+            "storage: {}, auth: {}, notifications: {} endpoints, health: {} checkers, \
+             proxy: {}, redis: {}, tls: {}",
+            self.storage,
+            auth,
+            self.notification_endpoints,
+            self.health_checkers,
+            on_off(self.proxy),
+            on_off(self.redis),
+            on_off(self.tls),
+        )
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "enabled"
+    } else {
+        "disabled"
+    }
+}
+
+impl Configuration {
+    // `summary` reports the subsystems enabled by the configuration.
+    pub fn summary(&self) -> ConfigSummary {
+        let health_checkers = self.health.as_ref().map_or(0, |health| {
+            let storage_driver = if health.stroage_driver.enabled { 1 } else { 0 };
+            health.file_checkers.len()
+                + health.http_checkers.len()
+                + health.tcp_checkers.len()
+                + storage_driver
+        });
+        ConfigSummary {
+            storage: self.storage.media.name().to_string(),
+            auth: self.auth.keys().cloned().collect(),
+            notification_endpoints: self.active_endpoints().count(),
+            health_checkers,
+            proxy: self.proxy_enabled(),
+            redis: self.redis_configured(),
+            tls: self.http.tls.enabled(),
+        }
+    }
+
+    // `metrics` reports the summary as prometheus gauges, named in the exposition format with
+    // any labels, so dashboards can track how deployed configurations drift.
+    pub fn metrics(&self) -> Vec<(String, f64)> {
+        let summary = self.summary();
+        let gauge = |name: &str, value: f64| (format!("registry_config_{}", name), value);
+        let flag = |enabled: bool| if enabled { 1.0 } else { 0.0 };
+        vec![
+            gauge(&format!("storage_driver{{driver=\"{}\"}}", summary.storage), 1.0),
+            gauge("auth_backends", summary.auth.len() as f64),
+            gauge("notification_endpoints", summary.notification_endpoints as f64),
+            gauge("health_checkers", summary.health_checkers as f64),
+            gauge("proxy_enabled", flag(summary.proxy)),
+            gauge("redis_enabled", flag(summary.redis)),
+            gauge("tls_enabled", flag(summary.tls)),
+        ]
+    }
+}
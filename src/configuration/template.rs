@@ -0,0 +1,117 @@
+use failure::bail;
+
+use nianjia::util::errors::NianjiaResult;
+
+// The storage drivers `template` can scaffold.
+pub const TEMPLATE_STORAGE: &[&str] = &["filesystem", "s3", "inmemory"];
+
+const HEADER: &str = "\
+# nianjia-registry configuration.
+#
+# Values marked TODO must be filled in before the registry is deployed.
+version: 0.1
+
+log:
+  # `level` is one of error, warn, info or debug.
+  level: info
+  # `formatter` is one of text or json.
+  formatter: text
+  fields:
+    service: registry
+
+";
+
+const FILESYSTEM: &str = "\
+storage:
+  filesystem:
+    # `rootdirectory` must be an absolute path.
+    rootdirectory: /var/lib/registry
+";
+
+const S3: &str = "\
+storage:
+  s3:
+    region: TODO
+    bucket: TODO
+    accesskey: TODO
+    secretkey: TODO
+    rootdirectory: /registry
+    encrypt: false
+    secure: true
+";
+
+const INMEMORY: &str = "\
+storage:
+  # The inmemory driver takes no parameters and loses its data on restart, use it for tests.
+  inmemory:
+";
+
+const FOOTER: &str = "\
+  delete:
+    enabled: false
+
+http:
+  addr: \":5000\"
+  # `secret` signs upload state; set the same random string on every replica.
+  secret: TODO
+  headers:
+    X-Content-Type-Options: [nosniff]
+
+# redis:
+#   addr: localhost:6379
+#   password: TODO
+#   db: 0
+#   pool:
+#     maxidle: 16
+#     maxactive: 64
+
+notifications:
+  endpoints: []
+  # - name: listener
+  #   url: https://listener.example.com/event
+  #   headers: {}
+  #   ignoredmediatypes: []
+
+health:
+  storagedriver:
+    enabled: true
+    interval: 10s
+    threshold: 3
+
+# proxy:
+#   remoteurl: https://registry-1.docker.io
+#   username: TODO
+#   password: TODO
+
+compatibility:
+  schema1:
+    enabled: false
+
+validation:
+  enabled: false
+  manifests:
+    urls:
+      allow: []
+      deny: []
+
+policy:
+  repository:
+    classes: []
+
+features: {}
+";
+
+// `template` returns a commented starting configuration using the `storage` driver.
+pub fn template(storage: &str) -> NianjiaResult<String> {
+    let storage = match storage {
+        "filesystem" => FILESYSTEM,
+        "s3" => S3,
+        "inmemory" => INMEMORY,
+        _ => bail!(
+            "unknown storage driver \"{}\", expected one of {}",
+            storage,
+            TEMPLATE_STORAGE.join(", ")
+        ),
+    };
+    Ok(format!("{}{}{}", HEADER, storage, FOOTER))
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use super::{Urls, Validation};
+
+// CompiledUrls caches the compiled `allow` and `deny` patterns of `Urls`, built on first use. It
+// takes no part in comparison, and a clone starts with an empty cache.
+#[derive(Default)]
+pub(super) struct CompiledUrls(OnceCell<Result<UrlRegexes, String>>);
+
+pub(super) struct UrlRegexes {
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl fmt::Debug for CompiledUrls {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CompiledUrls")
+    }
+}
+
+impl PartialEq for CompiledUrls {
+    fn eq(&self, _: &CompiledUrls) -> bool {
+        true
+    }
+}
+
+impl Clone for CompiledUrls {
+    fn clone(&self) -> CompiledUrls {
+        CompiledUrls::default()
+    }
+}
+
+impl Urls {
+    // `is_empty` reports whether there are neither `allow` nor `deny` rules.
+    pub(super) fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    // `regexes` compiles the patterns on first use, reporting the first which doesn't compile.
+    pub(super) fn regexes(&self) -> Result<&UrlRegexes, &str> {
+        self.compiled
+            .0
+            .get_or_init(|| {
+                Ok(UrlRegexes {
+                    allow: compile_all("allow", &self.allow)?,
+                    deny: compile_all("deny", &self.deny)?,
+                })
+            })
+            .as_ref()
+            .map_err(|e| e.as_str())
+    }
+}
+
+fn compile_all(list: &str, patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .enumerate()
+        .map(|(i, pattern)| {
+            if let Some(construct) = unsupported_construct(pattern) {
+                return Err(format!(
+                    "validation.manifests.urls.{}[{}] \"{}\" uses an unsupported construct: {}",
+                    list, i, pattern, construct
+                ));
+            }
+            Regex::new(pattern).map_err(|e| {
+                format!(
+                    "validation.manifests.urls.{}[{}] \"{}\" is not a valid regular expression: {}",
+                    list, i, pattern, e
+                )
+            })
+        })
+        .collect()
+}
+
+// `unsupported_construct` names the first construct in `pattern` which the `regex` crate doesn't
+// support, though configurations ported from other registries may use it: backreferences,
+// lookaround, atomic groups and `\Q...\E` quoting.
+pub fn unsupported_construct(pattern: &str) -> Option<&'static str> {
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('1'..='9') => return Some("backreference"),
+                Some('k') if chars.peek().map_or(false, |c| "<{'".contains(*c)) => {
+                    return Some("named backreference")
+                }
+                Some('Q') if !in_class => return Some("\\Q...\\E quoting"),
+                _ => {}
+            },
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class && chars.peek() == Some(&'?') => {
+                let rest: String = chars.clone().take(3).collect();
+                if rest.starts_with("?=") || rest.starts_with("?!") {
+                    return Some("lookahead");
+                }
+                if rest == "?<=" || rest == "?<!" {
+                    return Some("lookbehind");
+                }
+                if rest.starts_with("?>") {
+                    return Some("atomic group");
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Validation {
+    // `url_permitted` reports whether a url referenced by a pushed manifest is acceptable: it
+    // must match at least one `allow` pattern, unless none are set, and no `deny` pattern. A
+    // pattern which doesn't compile rejects every url.
+    pub fn url_permitted(&self, url: &str) -> bool {
+        match self.manifests.urls.regexes() {
+            Ok(regexes) => {
+                (regexes.allow.is_empty() || regexes.allow.iter().any(|re| re.is_match(url)))
+                    && !regexes.deny.iter().any(|re| re.is_match(url))
+            }
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,661 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration as StdDuration;
+
+use url::Url;
+
+use super::defaults::fill_interval;
+use super::middleware::{CloudFrontMiddleware, CLOUDFRONT_MIDDLEWARE};
+use super::section::SectionContext;
+use super::{
+    is_inline_pem, Configuration, Duration, Health, Maintenance, Notifications, Parameter,
+    KNOWN_FEATURES,
+};
+
+// The smallest interval a health checker may poll at; anything shorter would hammer the system.
+pub const MIN_HEALTH_INTERVAL: StdDuration = StdDuration::from_secs(1);
+// The longest a health checker may take to report a failure, polling `threshold` times.
+pub const MAX_HEALTH_DETECTION: StdDuration = StdDuration::from_secs(60 * 60);
+// The shortest http read timeout accepted; a slow client couldn't send the headers in less.
+pub const MIN_HTTP_READ_TIMEOUT: StdDuration = StdDuration::from_secs(1);
+// The largest redis timeout accepted; anything longer is most likely a unit mistake.
+pub const MAX_REDIS_TIMEOUT: StdDuration = StdDuration::from_secs(60 * 60);
+
+// New relic license keys are 40 characters long, whether the older hex keys or the newer ones
+// ending in `NRAL`.
+pub(super) const NEW_RELIC_LICENSE_KEY_LEN: usize = 40;
+
+// The middlewares which audit requests from the access log, and so record nothing while it is
+// disabled.
+const ACCESS_LOG_MIDDLEWARES: &[&str] = &["audit", "accesslog"];
+
+// ValidationErrors collects every problem found by `Configuration::validate`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidationErrors(Vec<String>);
+
+impl ValidationErrors {
+    pub fn errors(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn push(&mut self, error: String) {
+        self.0.push(error);
+    }
+
+    pub(super) fn extend(&mut self, other: ValidationErrors) {
+        self.0.extend(other.0);
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join("\n"))
+    }
+}
+
+impl error::Error for ValidationErrors {}
+
+// EndpointTlsIssue is a notification endpoint whose url scheme contradicts its tls settings. An
+// `Error` can't work, a `Warning` works but isn't what the settings suggest.
+#[derive(Debug, PartialEq)]
+pub enum EndpointTlsIssue {
+    Error(String),
+    Warning(String),
+}
+
+impl Notifications {
+    // `validate_endpoint_reachability_config` checks the url scheme of every enabled endpoint
+    // against its tls settings. A client certificate is only presented over https, so setting
+    // one on a plain http endpoint is an error; skipping verification over https works, but is
+    // reported, as is skipping it over plain http, where there's nothing to verify.
+    pub fn validate_endpoint_reachability_config(&self) -> Vec<EndpointTlsIssue> {
+        let mut issues = Vec::new();
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if endpoint.disabled {
+                continue;
+            }
+            let url = match Url::parse(&endpoint.url) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            let prefix = format!("notifications.endpoints[{}]", i);
+            let insecure = endpoint.insecure_skip_verify;
+            match url.scheme() {
+                "https" if insecure => issues.push(EndpointTlsIssue::Warning(format!(
+                    "{}.insecureskipverify is set, the certificate of {} isn't verified",
+                    prefix, endpoint.url
+                ))),
+                "http" => {
+                    if !endpoint.client_cert.is_empty() || !endpoint.client_key.is_empty() {
+                        issues.push(EndpointTlsIssue::Error(format!(
+                            "{}.clientcert is set, but {} is plain http, which presents no \
+                             client certificate",
+                            prefix, endpoint.url
+                        )));
+                    }
+                    if insecure {
+                        issues.push(EndpointTlsIssue::Warning(format!(
+                            "{}.insecureskipverify has no effect, {} is plain http",
+                            prefix, endpoint.url
+                        )));
+                    }
+                }
+                _ => {}
+            }
+        }
+        issues
+    }
+}
+
+impl Configuration {
+    // `validate` checks the configuration for settings which can't work, reporting all of them
+    // at once.
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        self.validate_under(None)
+    }
+
+    // `validate_paths_relative_to` validates the configuration as it will be seen once the files
+    // it references are mounted under `base`, as when a config is baked into an image: every path
+    // the checks open, absolute or relative, is resolved under `base` rather than on this host.
+    pub fn validate_paths_relative_to(&self, base: &Path) -> Result<(), ValidationErrors> {
+        self.validate_under(Some(base))
+    }
+
+    fn validate_under(&self, root: Option<&Path>) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        self.validate_instance_name(&mut errors);
+        let ctx = SectionContext { root };
+        for section in self.sections() {
+            errors.extend(section.validate(&ctx));
+        }
+        self.validate_auth_order(&mut errors);
+        self.validate_required_keys(&mut errors);
+        self.validate_redis_required(&mut errors);
+        self.validate_proxy_read_only(&mut errors);
+        self.validate_cloudfront(&mut errors, root);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // `warnings` reports settings which are accepted but likely to cause problems at runtime.
+    //
+    // Some warnings are about sections which are fine on their own, but contradict each other.
+    // They name the settings on both sides, leaving the operator to decide which one is wrong:
+    //
+    // - `proxy.remoteurl` against `storage.maintenance.readonly.enabled: false`.
+    // - `storage.redirect` against a storage driver which can't redirect.
+    // - `http.tls.clientcas` against plain http.
+    // - `log.access_log.disabled` against the middlewares in `ACCESS_LOG_MIDDLEWARES`.
+    // - `notifications.endpoints[].insecureskipverify` against the scheme of the endpoint url.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        self.warn_prometheus_path(&mut warnings);
+        self.warn_endpoint_header_placeholders(&mut warnings);
+        self.warn_proxy_read_only(&mut warnings);
+        self.warn_read_only_text(&mut warnings);
+        self.warn_redirect_unsupported(&mut warnings);
+        self.warn_client_cas_without_tls(&mut warnings);
+        self.warn_access_log_consumers(&mut warnings);
+        self.warn_shared_paths(&mut warnings);
+        for issue in self.notifications.validate_endpoint_reachability_config() {
+            if let EndpointTlsIssue::Warning(warning) = issue {
+                warnings.push(warning);
+            }
+        }
+        warnings.extend(self.unknown_feature_warnings());
+        warnings
+    }
+
+    fn unknown_feature_warnings(&self) -> Vec<String> {
+        self.features
+            .keys()
+            .filter(|name| !KNOWN_FEATURES.contains(&name.as_str()))
+            .map(|name| format!("features.{} is not a known feature", name))
+            .collect()
+    }
+
+    // `warnings_as_errors` reports the `warnings` and the deprecated fields in use as errors, for
+    // gates which hold configurations to best practice. Unknown feature flags are left out:
+    // `KNOWN_FEATURES` is empty until a feature ships, and a flag may be meant for a newer
+    // registry.
+    pub fn warnings_as_errors(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let unknown_features = self.unknown_feature_warnings();
+        for warning in self.warnings() {
+            if !unknown_features.contains(&warning) {
+                errors.push(warning);
+            }
+        }
+        for deprecation in self.deprecations() {
+            errors.push(deprecation.to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_instance_name(&self, errors: &mut ValidationErrors) {
+        let name = &self.instance_name;
+        if name.is_empty() {
+            return;
+        }
+        let dns_safe = name.len() <= 253
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            && !name.starts_with(|c| c == '-' || c == '.')
+            && !name.ends_with(|c| c == '-' || c == '.');
+        if !dns_safe {
+            errors.push(format!(
+                "instancename \"{}\" may only contain letters, digits, '-' and '.'",
+                name
+            ));
+        }
+    }
+
+    fn validate_auth_order(&self, errors: &mut ValidationErrors) {
+        let mut seen = HashSet::new();
+        for name in &self.auth_order {
+            if !self.auth.contains_key(name) {
+                errors.push(format!(
+                    "authorder lists \"{}\", which isn't configured in auth",
+                    name
+                ));
+            } else if !seen.insert(name) {
+                errors.push(format!("authorder lists \"{}\" more than once", name));
+            }
+        }
+    }
+
+    // Required keys are only enforced for the entries which are enabled, so that an entry can be
+    // switched off while it is being filled in.
+    fn validate_required_keys(&self, errors: &mut ValidationErrors) {
+        for (subsystem, i, middleware) in self.enabled_middlewares() {
+            let prefix = format!("middleware.{}[{}]", subsystem, i);
+            require(errors, &prefix, "name", &middleware.name);
+            for option in middleware.missing_options() {
+                errors.push(format!(
+                    "{}.options.{} is required by the {} middleware",
+                    prefix, option, middleware.name
+                ));
+            }
+        }
+    }
+
+    fn validate_redis_required(&self, errors: &mut ValidationErrors) {
+        if self.redis_configured() {
+            return;
+        }
+        let consumers = self.redis_consumers();
+        if !consumers.is_empty() {
+            errors.push(format!(
+                "redis.addr must be set, redis is used by {}",
+                consumers.join(", ")
+            ));
+        }
+    }
+
+    fn validate_proxy_read_only(&self, errors: &mut ValidationErrors) {
+        if !self.proxy_enabled() {
+            return;
+        }
+        if let Some(delete) = &self.storage.delete {
+            if flag(&(delete.0).parameters, "enabled") == Some(true) {
+                errors.push(
+                    "storage.delete.enabled can't be set when proxy.remoteurl is set, a pull \
+                     through cache is read-only"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    // The options `cloudfront` requires are checked with those of other middlewares.
+    fn validate_cloudfront(&self, errors: &mut ValidationErrors, root: Option<&Path>) {
+        for (subsystem, i, middleware) in self.enabled_middlewares() {
+            if middleware.name != CLOUDFRONT_MIDDLEWARE || !middleware.missing_options().is_empty()
+            {
+                continue;
+            }
+            let prefix = format!("middleware.{}[{}]", subsystem, i);
+            if subsystem != "storage" {
+                errors.push(format!(
+                    "{} \"{}\" is a storage middleware, it only works under middleware.storage",
+                    prefix, CLOUDFRONT_MIDDLEWARE
+                ));
+                continue;
+            }
+            let options = &middleware.options.parameters;
+            let checked = CloudFrontMiddleware::from_options(&prefix, options)
+                .and_then(|cloudfront| cloudfront.validate(&prefix, root));
+            if let Err(e) = checked {
+                errors.push(e.to_string());
+            }
+        }
+    }
+
+    fn warn_proxy_read_only(&self, warnings: &mut Vec<String>) {
+        if !self.proxy_enabled() {
+            return;
+        }
+        if let Some(maintenance) = &self.storage.maintenance {
+            if flag(&maintenance.readonly.parameters, "enabled") == Some(false) {
+                warnings.push(
+                    "storage.maintenance.readonly.enabled is false, but a pull through cache \
+                     (proxy.remoteurl) is always read-only"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    fn warn_access_log_consumers(&self, warnings: &mut Vec<String>) {
+        if !self.log.access_log.disabled {
+            return;
+        }
+        for (subsystem, i, middleware) in self.enabled_middlewares() {
+            if ACCESS_LOG_MIDDLEWARES.contains(&middleware.name.as_str()) {
+                warnings.push(format!(
+                    "middleware.{}[{}] \"{}\" audits the access log, but \
+                     log.access_log.disabled is set",
+                    subsystem, i, middleware.name
+                ));
+            }
+        }
+    }
+
+    fn warn_client_cas_without_tls(&self, warnings: &mut Vec<String>) {
+        let tls = &self.http.tls;
+        if tls.requires_client_certs() && !tls.enabled() {
+            warnings.push(
+                "http.tls.clientcas is set, but client certificates are only verified when \
+                 http.tls serves https"
+                    .to_string(),
+            );
+        }
+    }
+
+    // A file serves one purpose: a certificate isn't a key, and a cache the registry writes isn't
+    // a marker a health check looks for. Entries of the same list, such as two health file
+    // checkers on one file, serve the same purpose.
+    fn warn_shared_paths(&self, warnings: &mut Vec<String>) {
+        let paths = self.referenced_paths();
+        let mut earlier: HashMap<&Path, Vec<(&str, String)>> = HashMap::new();
+        for (key, path) in &paths {
+            let purpose = purpose(key);
+            let same_path = earlier.entry(path.as_path()).or_default();
+            if let Some((other, _)) = same_path.iter().find(|(_, other)| *other != purpose) {
+                warnings.push(format!(
+                    "{} and {} both refer to {}, which can't serve both",
+                    other,
+                    key,
+                    path.display()
+                ));
+            }
+            same_path.push((key.as_str(), purpose));
+        }
+    }
+
+    fn warn_redirect_unsupported(&self, warnings: &mut Vec<String>) {
+        let media = &self.storage.media;
+        if self.storage.redirect.is_some() && !media.supports_redirect() {
+            warnings.push(format!(
+                "storage.redirect has no effect with the {} storage driver, only object stores \
+                 redirect",
+                media.name()
+            ));
+        }
+    }
+
+    fn warn_read_only_text(&self, warnings: &mut Vec<String>) {
+        let read_only = self.storage.maintenance.as_ref().map(Maintenance::read_only);
+        if let Some(Ok(read_only)) = read_only {
+            if !read_only.enabled && read_only.text.is_some() {
+                warnings.push(
+                    "storage.maintenance.readonly.text is set, but read-only mode is not enabled"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    fn warn_endpoint_header_placeholders(&self, warnings: &mut Vec<String>) {
+        for (i, endpoint) in self.active_endpoints() {
+            for (name, values) in &endpoint.headers {
+                if values.iter().any(|value| has_placeholder(value)) {
+                    warnings.push(format!(
+                        "notifications.endpoints[{}].headers[\"{}\"] contains unresolved \
+                         placeholder",
+                        i, name
+                    ));
+                }
+            }
+        }
+    }
+
+    // When the debug server shares the main listener, a metrics path under `http.prefix` would
+    // shadow the registry's own routes.
+    fn warn_prometheus_path(&self, warnings: &mut Vec<String>) {
+        let debug = &self.http.debug;
+        if !debug.prometheus.enabled || !debug.addr.is_empty() {
+            return;
+        }
+        let prefix = normalize_prefix(&self.http.prefix);
+        if prefix == "/" {
+            return;
+        }
+        let path = debug.prometheus.path();
+        if path.starts_with(&prefix) || path == prefix.trim_end_matches('/') {
+            warnings.push(format!(
+                "debug.prometheus.path \"{}\" falls under http.prefix \"{}\" while debug.addr is \
+                 empty; set debug.addr to serve metrics on a separate listener",
+                path, self.http.prefix
+            ));
+        }
+    }
+}
+
+// `flag` returns the boolean parameter `key`, if it is set.
+pub(super) fn flag(parameters: &BTreeMap<String, Parameter>, key: &str) -> Option<bool> {
+    match parameters.get(key) {
+        Some(Parameter::Boolean(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+// PemKind is what a pem setting is expected to hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum PemKind {
+    Certificate,
+    PrivateKey,
+}
+
+impl PemKind {
+    // `accepts` tells whether a pem block tagged `tag` holds this kind, counting the `RSA` and
+    // `EC` private keys as well as pkcs8 ones.
+    fn accepts(self, tag: &str) -> bool {
+        match self {
+            PemKind::Certificate => tag == "CERTIFICATE",
+            PemKind::PrivateKey => tag.ends_with("PRIVATE KEY"),
+        }
+    }
+}
+
+impl fmt::Display for PemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PemKind::Certificate => write!(f, "certificate"),
+            PemKind::PrivateKey => write!(f, "private key"),
+        }
+    }
+}
+
+// `check_pem` checks that `value`, inline pem or the path of a pem file, holds pem data of the
+// `kind` expected.
+pub(super) fn check_pem(
+    errors: &mut ValidationErrors,
+    root: Option<&Path>,
+    key: &str,
+    value: &str,
+    kind: PemKind,
+) {
+    if value.is_empty() {
+        return;
+    }
+    let bytes = if is_inline_pem(value) {
+        value.as_bytes().to_vec()
+    } else {
+        match fs::read(resolve(root, Path::new(value))) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                errors.push(format!("{} \"{}\": {}", key, value, e));
+                return;
+            }
+        }
+    };
+    match pem::parse(&bytes) {
+        Err(e) => errors.push(format!("{} is not valid pem: {}", key, e)),
+        Ok(pem) if !kind.accepts(&pem.tag) => errors.push(format!(
+            "{} holds a pem {} rather than a {}",
+            key, pem.tag, kind
+        )),
+        Ok(_) => {}
+    }
+}
+
+// `resolve` places `path` under `root`, when set, dropping any leading `/` so that absolute paths
+// land inside it too.
+pub(super) fn resolve(root: Option<&Path>, path: &Path) -> PathBuf {
+    match root {
+        Some(root) => root.join(
+            path.components()
+                .filter(|c| match c {
+                    Component::RootDir | Component::Prefix(_) => false,
+                    _ => true,
+                })
+                .collect::<PathBuf>(),
+        ),
+        None => path.to_path_buf(),
+    }
+}
+
+pub(super) fn require(errors: &mut ValidationErrors, prefix: &str, key: &str, value: &str) {
+    if value.trim().is_empty() {
+        errors.push(format!("{}.{} is required", prefix, key));
+    }
+}
+
+// `validate_health_durations` checks the durations of every health checker, an interval left
+// unset at the default it takes. The storage driver checker is only checked when it's enabled.
+pub(super) fn validate_health_durations(errors: &mut ValidationErrors, health: &Health) {
+    for (i, checker) in health.file_checkers.iter().enumerate() {
+        let label = format!("health.file[{}]", i);
+        let interval = effective_interval(&checker.interval);
+        validate_checker_common(errors, &label, None, &interval, checker.threshold);
+    }
+    for (i, checker) in health.http_checkers.iter().enumerate() {
+        let label = format!("health.http[{}]", i);
+        let timeout = Some(&checker.timeout);
+        let interval = effective_interval(&checker.interval);
+        validate_checker_common(errors, &label, timeout, &interval, checker.threshold);
+    }
+    for (i, checker) in health.tcp_checkers.iter().enumerate() {
+        let label = format!("health.tcp[{}]", i);
+        let timeout = Some(&checker.timeout);
+        let interval = effective_interval(&checker.interval);
+        validate_checker_common(errors, &label, timeout, &interval, checker.threshold);
+    }
+    if let Some((interval, threshold)) = health.stroage_driver.schedule() {
+        validate_checker_common(errors, "health.storagedriver", None, &interval, threshold);
+    }
+}
+
+// `effective_interval` is the interval a checker polls at, the default when it's left unset.
+fn effective_interval(interval: &Duration) -> Duration {
+    let mut interval = interval.clone();
+    fill_interval(&mut interval);
+    interval
+}
+
+// `validate_checker_common` checks the settings every health checker shares: the `interval` it
+// polls at, the `timeout` of a single check, which has to expire by the time the next is due, and
+// the `threshold` of failures, which together with the interval must report a failure within
+// `MAX_HEALTH_DETECTION`.
+fn validate_checker_common(
+    errors: &mut ValidationErrors,
+    label: &str,
+    timeout: Option<&Duration>,
+    interval: &Duration,
+    threshold: u32,
+) {
+    let key = format!("{}.interval", label);
+    check_min_duration(errors, &key, interval, MIN_HEALTH_INTERVAL);
+    if let Some(timeout) = timeout {
+        if interval.as_std() >= MIN_HEALTH_INTERVAL && timeout.as_std() > interval.as_std() {
+            errors.push(format!(
+                "{}.timeout {} must not be longer than the interval of {}",
+                label, timeout, interval
+            ));
+        }
+    }
+    let detection = interval.as_std().checked_mul(threshold.max(1));
+    if detection.map_or(true, |detection| detection > MAX_HEALTH_DETECTION) {
+        errors.push(format!(
+            "{}.threshold {} at an interval of {} takes over {} to report a failure",
+            label,
+            threshold,
+            interval,
+            humantime::format_duration(MAX_HEALTH_DETECTION)
+        ));
+    }
+}
+
+pub(super) fn check_min_duration(
+    errors: &mut ValidationErrors,
+    key: &str,
+    value: &Duration,
+    min: StdDuration,
+) {
+    if value.as_std() < min {
+        errors.push(format!(
+            "{} {} is below the minimum of {}",
+            key,
+            value,
+            humantime::format_duration(min)
+        ));
+    }
+}
+
+pub(super) fn check_max_duration(
+    errors: &mut ValidationErrors,
+    key: &str,
+    value: &Duration,
+    max: StdDuration,
+) {
+    if value.as_std() > max {
+        errors.push(format!(
+            "{} {} is above the maximum of {}",
+            key,
+            value,
+            humantime::format_duration(max)
+        ));
+    }
+}
+
+// `is_identifier` accepts an ascii alphanumeric character followed by alphanumerics, `.`, `_` and
+// `-`.
+pub(super) fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphanumeric() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+        }
+        _ => false,
+    }
+}
+
+// `has_placeholder` reports whether `value` still contains a `<...>` template token.
+fn has_placeholder(value: &str) -> bool {
+    match value.find('<') {
+        Some(start) => value[start + 1..].find('>').map_or(false, |len| len > 0),
+        None => false,
+    }
+}
+
+// `purpose` returns the key a path was read from without its list indexes, so that
+// `health.file[0].file` and `health.file[1].file` serve the same one.
+fn purpose(key: &str) -> String {
+    let mut purpose = String::with_capacity(key.len());
+    let mut in_index = false;
+    for c in key.chars() {
+        match c {
+            '[' => in_index = true,
+            ']' => in_index = false,
+            _ if !in_index => purpose.push(c),
+            _ => {}
+        }
+    }
+    purpose
+}
+
+// `normalize_prefix` gives `prefix` a leading and a trailing slash.
+fn normalize_prefix(prefix: &str) -> String {
+    let trimmed = prefix.trim_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", trimmed)
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use failure::{bail, format_err};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::references::{field, SecretProvider, SecretReference};
+use super::REMOTE_CONFIG_TIMEOUT;
+
+// VaultSecrets resolves `vault://path#key` from the HashiCorp Vault secret at `path`, such as
+// `vault://secret/data/registry#redis_password`. Both the kv version 2 engine, whose fields are
+// under `data.data`, and version 1, whose fields are under `data`, are read.
+pub struct VaultSecrets {
+    address: String,
+    token: String,
+    client: reqwest::Client,
+}
+
+impl VaultSecrets {
+    // `new` returns a provider reading from the Vault server at `address`, such as
+    // `https://vault.example.com:8200`, with `token`.
+    pub fn new(address: &str, token: &str) -> NianjiaResult<VaultSecrets> {
+        let client = reqwest::Client::builder()
+            .timeout(REMOTE_CONFIG_TIMEOUT)
+            .build()?;
+        Ok(VaultSecrets {
+            address: address.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            client,
+        })
+    }
+
+    // `from_env` returns a provider for the server and token in `VAULT_ADDR` and `VAULT_TOKEN`,
+    // as the vault cli reads them.
+    pub fn from_env() -> NianjiaResult<VaultSecrets> {
+        let address = env::var("VAULT_ADDR").map_err(|e| format_err!("VAULT_ADDR: {}", e))?;
+        let token = env::var("VAULT_TOKEN").map_err(|e| format_err!("VAULT_TOKEN: {}", e))?;
+        VaultSecrets::new(&address, &token)
+    }
+
+    fn read(&self, path: &str) -> NianjiaResult<BTreeMap<String, serde_yaml::Value>> {
+        let url = format!("{}/v1/{}", self.address, path.trim_start_matches('/'));
+        let mut response = self
+            .client
+            .get(&url)
+            .header("X-Vault-Token", self.token.as_str())
+            .send()
+            .map_err(|e| format_err!("failed to read vault secret {}: {}", path, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "failed to read vault secret {}: vault answered {}",
+                path,
+                status
+            );
+        }
+        let body = response
+            .text()
+            .map_err(|e| format_err!("failed to read vault secret {}: {}", path, e))?;
+        // json is yaml, so the crate's yaml parser reads vault's answers.
+        let body: serde_yaml::Value = serde_yaml::from_str(&body)
+            .map_err(|e| format_err!("vault secret {} is not json: {}", path, e))?;
+        let data = &body["data"];
+        let fields = match data.get("data") {
+            Some(fields) if fields.is_mapping() => fields,
+            _ => data,
+        };
+        serde_yaml::from_value(fields.clone())
+            .map_err(|_| format_err!("vault secret {} holds no fields", path))
+    }
+}
+
+impl SecretProvider for VaultSecrets {
+    fn scheme(&self) -> &str {
+        "vault"
+    }
+
+    fn resolve(&self, reference: &SecretReference) -> NianjiaResult<String> {
+        let key = match &reference.key {
+            Some(key) => key,
+            None => bail!(
+                "a vault secret reference must name a key, as in {}#key",
+                reference
+            ),
+        };
+        field(&self.read(&reference.path)?, key)
+    }
+
+    // Each secret is read once, however many of its keys are referenced.
+    fn resolve_all(&self, references: &[SecretReference]) -> Vec<NianjiaResult<String>> {
+        let mut secrets = BTreeMap::new();
+        references
+            .iter()
+            .map(|reference| {
+                let key = match &reference.key {
+                    Some(key) => key,
+                    None => return self.resolve(reference),
+                };
+                let fields = secrets
+                    .entry(reference.path.as_str())
+                    .or_insert_with(|| self.read(&reference.path).map_err(|e| e.to_string()));
+                match fields {
+                    Ok(fields) => field(fields, key),
+                    Err(e) => Err(format_err!("{}", e)),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use failure::format_err;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use nianjia::util::errors::NianjiaResult;
+
+use super::{parse_file, Configuration};
+
+// How long the watcher waits for writes to a config file to settle before reloading it, so that
+// an editor saving in several steps triggers a single reload.
+pub const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
+// ConfigWatcher reloads a configuration file whenever it changes, for as long as it is alive.
+pub struct ConfigWatcher {
+    // Dropping the notify watcher ends the reload thread.
+    _watcher: RecommendedWatcher,
+    last_good: Arc<Mutex<Configuration>>,
+}
+
+impl ConfigWatcher {
+    // `last_good` returns the configuration most recently loaded without error.
+    pub fn last_good(&self) -> Configuration {
+        match self.last_good.lock() {
+            Ok(config) => config.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+// `watch` loads the configuration file at `path`, then reloads it each time it is written,
+// created or replaced, as editors do when saving. Every reload is parsed and validated, and
+// `callback` gets the new configuration or the error. A configuration which fails is never
+// kept: `last_good` goes on returning the previous one. `watch` fails if the file doesn't load
+// to begin with.
+pub fn watch<F>(path: &str, mut callback: F) -> NianjiaResult<ConfigWatcher>
+where
+    F: FnMut(NianjiaResult<Configuration>) + Send + 'static,
+{
+    let initial = load(path)?;
+    let file = PathBuf::from(path);
+    // The directory is watched rather than the file, since replacing the file would end a watch
+    // on it.
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, WATCH_DEBOUNCE)?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    let last_good = Arc::new(Mutex::new(initial));
+    let shared = Arc::clone(&last_good);
+    let path = path.to_string();
+    thread::spawn(move || {
+        for event in rx {
+            let changed = match &event {
+                DebouncedEvent::Write(changed)
+                | DebouncedEvent::Create(changed)
+                | DebouncedEvent::Chmod(changed)
+                | DebouncedEvent::Rename(_, changed) => changed,
+                _ => continue,
+            };
+            if !same_file(changed, &file) {
+                continue;
+            }
+            let reloaded = load(&path);
+            if let Ok(config) = &reloaded {
+                if let Ok(mut last_good) = shared.lock() {
+                    *last_good = config.clone();
+                }
+            }
+            callback(reloaded);
+        }
+    });
+    Ok(ConfigWatcher {
+        _watcher: watcher,
+        last_good,
+    })
+}
+
+fn load(path: &str) -> NianjiaResult<Configuration> {
+    let config = parse_file(path)?;
+    config
+        .validate()
+        .map_err(|e| format_err!("invalid config file \"{}\": {}", path, e))?;
+    Ok(config)
+}
+
+// `same_file` compares the file names only: the events name paths in the watched directory,
+// which notify may have resolved differently from `file`.
+fn same_file(changed: &Path, file: &Path) -> bool {
+    changed.file_name() == file.file_name()
+}
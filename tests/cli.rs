@@ -0,0 +1,144 @@
+use std::fs;
+use std::process::Command;
+
+use registry::configuration::parse_str;
+
+// `exit_code` runs the registry with `config` written to a temporary file, or with a path which
+// doesn't exist when `config` is None, and returns its exit code.
+fn exit_code(config: Option<&str>) -> i32 {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    if let Some(config) = config {
+        fs::write(&path, config).unwrap();
+    }
+    Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+        .arg("-c")
+        .arg(&path)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(exit_code(Some("version: 0.1\nlog: {}\n")), 0);
+    assert_eq!(exit_code(Some("version: 0.1\nlog: [unclosed\n")), 1);
+    assert_eq!(
+        exit_code(Some("version: 0.1\nlog: {}\ninstancename: -registry-\n")),
+        2
+    );
+    assert_eq!(exit_code(None), 3);
+}
+
+#[test]
+fn test_print_keeps_warnings_off_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    fs::write(&path, "version: 0.1\nlog: {}\nloglevel: debug\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+        .arg("-c")
+        .arg(&path)
+        .arg("--print")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("loglevel is deprecated"), "{}", stderr);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(parse_str(&stdout).is_ok(), "{}", stdout);
+}
+
+#[test]
+fn test_set_keeps_comments() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    let config = "# registry config\nversion: 0.1\nlog: {}\n\n# the listener\nhttp:\n  \
+                  addr: localhost:5000 # public port\n  secret: changeme\n";
+    fs::write(&path, config).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+        .arg("-c")
+        .arg(&path)
+        .args(&[
+            "--set",
+            "http.addr=localhost:6000",
+            "--set",
+            "http.host=registry.example.com",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# registry config\nversion: 0.1\nlog: {}\n\n# the listener\nhttp:\n  \
+         addr: localhost:6000 # public port\n  secret: changeme\n  host: registry.example.com\n"
+    );
+}
+
+#[test]
+fn test_diff() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.yaml");
+    let new = dir.path().join("new.yaml");
+    fs::write(
+        &old,
+        "version: 0.1\nlog:\n  formatter: text\nhttp:\n  addr: localhost:5000\n  secret: one\n",
+    )
+    .unwrap();
+    fs::write(
+        &new,
+        "version: 0.1\nlog:\n  formatter: json\nhttp:\n  addr: localhost:5000\n  secret: two\n  \
+         host: registry.example.com\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+        .arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "http.host: \"\" -> registry.example.com\nhttp.secret: REDACTED -> REDACTED\n\
+         log.formatter: text -> json\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+        .arg("diff")
+        .arg(&old)
+        .arg(&old)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_config_from_env() {
+    let home = tempfile::tempdir().unwrap();
+    fs::write(home.path().join("valid.yaml"), "version: 0.1\nlog: {}\n").unwrap();
+    fs::write(
+        home.path().join("invalid.yaml"),
+        "version: 0.1\nlog: {}\ninstancename: -registry-\n",
+    )
+    .unwrap();
+    let exit_code = |config: &str, args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_nianjia-registry"))
+            .args(args)
+            .env("NIANJIA_HOME", home.path())
+            .env("NIANJIA_CONFIG", config)
+            .output()
+            .unwrap()
+            .status
+            .code()
+            .unwrap()
+    };
+    assert_eq!(exit_code("valid.yaml", &[]), 0);
+    assert_eq!(exit_code("invalid.yaml", &[]), 2);
+    let valid = home.path().join("valid.yaml");
+    assert_eq!(exit_code(valid.to_str().unwrap(), &[]), 0);
+    // -c takes precedence over NIANJIA_CONFIG.
+    assert_eq!(exit_code("valid.yaml", &["-c", "missing.yaml"]), 3);
+}